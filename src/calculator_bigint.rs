@@ -0,0 +1,597 @@
+#[allow(dead_code)]
+pub mod bigint{
+    use std::{
+        cmp::Ordering,
+        fmt,
+        ops::{
+            Add,
+            Mul,
+            Neg,
+            Sub
+        }
+    };
+    use crate::calculator_model::model::{
+        EvalError,
+        Function
+    };
+    use crate::calculator_number::number::Number;
+
+    const DECIMAL_BASE: u32 = 1_000_000_000;
+    const DECIMAL_DIGITS: usize = 9;
+    // Larger factorials and binomials are refused rather than computed for minutes.
+    const MAX_FACTORIAL: u64 = 20_000;
+    // Powers whose result would need more bits than this are rejected.
+    const MAX_BITS: u64 = 1 << 24;
+
+    // Sign and magnitude, the magnitude is little endian base 2^32 without trailing zero limbs.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct BigInt{
+        negative: bool,
+        limbs: Vec<u32>
+    }
+
+    fn trim(mut limbs: Vec<u32>) -> Vec<u32>{
+        while limbs.last() == Some(&0){
+            limbs.pop();
+        }
+        limbs
+    }
+
+    fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering{
+        if a.len() != b.len(){
+            return a.len().cmp(&b.len());
+        }
+        for (x, y) in a.iter().rev().zip(b.iter().rev()){
+            if x != y{
+                return x.cmp(y);
+            }
+        }
+        Ordering::Equal
+    }
+
+    fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32>{
+        let (long, short) = if a.len() >= b.len() {(a, b)} else {(b, a)};
+        let mut out = Vec::with_capacity(long.len() + 1);
+        let mut carry = 0u64;
+        for (i, &x) in long.iter().enumerate(){
+            let sum = x as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+            out.push(sum as u32);
+            carry = sum >> 32;
+        }
+        if carry > 0{
+            out.push(carry as u32);
+        }
+        out
+    }
+
+    // Needs a >= b.
+    fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32>{
+        let mut out = Vec::with_capacity(a.len());
+        let mut borrow = 0i64;
+        for (i, &x) in a.iter().enumerate(){
+            let mut diff = x as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if diff < 0{
+                diff += 1 << 32;
+                borrow = 1;
+            }
+            out.push(diff as u32);
+        }
+        trim(out)
+    }
+
+    fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32>{
+        if a.is_empty() || b.is_empty(){
+            return Vec::new();
+        }
+        let mut out = vec![0u32; a.len() + b.len()];
+        for (i, &x) in a.iter().enumerate(){
+            let mut carry = 0u64;
+            for (j, &y) in b.iter().enumerate(){
+                let t = x as u64 * y as u64 + out[i + j] as u64 + carry;
+                out[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            out[i + b.len()] = carry as u32;
+        }
+        trim(out)
+    }
+
+    fn divrem_small(a: &[u32], d: u32) -> (Vec<u32>, u32){
+        let mut out = vec![0u32; a.len()];
+        let mut rem = 0u64;
+        for i in (0..a.len()).rev(){
+            let cur = (rem << 32) | a[i] as u64;
+            out[i] = (cur / d as u64) as u32;
+            rem = cur % d as u64;
+        }
+        (trim(out), rem as u32)
+    }
+
+    fn shl_mag(a: &[u32], bits: u64) -> Vec<u32>{
+        if a.is_empty(){
+            return Vec::new();
+        }
+        let (words, bits) = ((bits / 32) as usize, (bits % 32) as u32);
+        let mut out = vec![0u32; words];
+        let mut carry = 0u32;
+        for &x in a{
+            if bits == 0{
+                out.push(x);
+            }else{
+                out.push((x << bits) | carry);
+                carry = x >> (32 - bits);
+            }
+        }
+        if carry > 0{
+            out.push(carry);
+        }
+        out
+    }
+
+    fn shr_mag(a: &[u32], bits: u64) -> Vec<u32>{
+        let (words, bits) = ((bits / 32) as usize, (bits % 32) as u32);
+        if words >= a.len(){
+            return Vec::new();
+        }
+        let a = &a[words..];
+        let mut out = Vec::with_capacity(a.len());
+        for i in 0..a.len(){
+            if bits == 0{
+                out.push(a[i]);
+            }else{
+                let high = a.get(i + 1).map_or(0, |&h| h << (32 - bits));
+                out.push((a[i] >> bits) | high);
+            }
+        }
+        trim(out)
+    }
+
+    // Knuth's algorithm D, with the divisor normalized so its top limb has the high bit set.
+    fn divrem_mag(u: &[u32], v: &[u32]) -> (Vec<u32>, Vec<u32>){
+        if cmp_mag(u, v) == Ordering::Less{
+            return (Vec::new(), u.to_vec());
+        }
+        if v.len() == 1{
+            let (q, r) = divrem_small(u, v[0]);
+            return (q, trim(vec![r]));
+        }
+        let s = v[v.len() - 1].leading_zeros() as u64;
+        let vn = shl_mag(v, s);
+        let mut un = shl_mag(u, s);
+        if un.len() == u.len(){
+            un.push(0);
+        }
+        let n = vn.len();
+        let m = un.len() - n;
+        let mut q = vec![0u32; m];
+        let b = 1u64 << 32;
+        for j in (0..m).rev(){
+            let num = ((un[j + n] as u64) << 32) | un[j + n - 1] as u64;
+            let mut qhat = num / vn[n - 1] as u64;
+            let mut rhat = num % vn[n - 1] as u64;
+            while qhat >= b || qhat * vn[n - 2] as u64 > ((rhat << 32) | un[j + n - 2] as u64){
+                qhat -= 1;
+                rhat += vn[n - 1] as u64;
+                if rhat >= b{
+                    break;
+                }
+            }
+            let mut k = 0i64;
+            for i in 0..n{
+                let p = qhat * vn[i] as u64;
+                let t = un[i + j] as i64 - k - (p & 0xffff_ffff) as i64;
+                un[i + j] = t as u32;
+                k = (p >> 32) as i64 - (t >> 32);
+            }
+            let t = un[j + n] as i64 - k;
+            un[j + n] = t as u32;
+            q[j] = qhat as u32;
+            // qhat was one too large, add the divisor back
+            if t < 0{
+                q[j] = q[j].wrapping_sub(1);
+                let mut carry = 0u64;
+                for i in 0..n{
+                    let t = un[i + j] as u64 + vn[i] as u64 + carry;
+                    un[i + j] = t as u32;
+                    carry = t >> 32;
+                }
+                un[j + n] = un[j + n].wrapping_add(carry as u32);
+            }
+        }
+        un.truncate(n);
+        (trim(q), shr_mag(&trim(un), s))
+    }
+
+    impl BigInt{
+        fn from_parts(negative: bool, limbs: Vec<u32>) -> BigInt{
+            let limbs = trim(limbs);
+            BigInt{negative: negative && !limbs.is_empty(), limbs}
+        }
+
+        pub fn zero() -> BigInt{
+            BigInt{negative: false, limbs: Vec::new()}
+        }
+
+        pub fn one() -> BigInt{
+            BigInt::from(1u64)
+        }
+
+        // Optional sign followed by decimal digits.
+        pub fn parse(src: &str) -> Option<BigInt>{
+            let (negative, digits) = match src.strip_prefix('-'){
+                Some(rest) => (true, rest),
+                None => (false, src.strip_prefix('+').unwrap_or(src))
+            };
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()){
+                return None;
+            }
+            let mut limbs = Vec::new();
+            let mut start = 0;
+            let mut end = match digits.len() % DECIMAL_DIGITS{
+                0 => DECIMAL_DIGITS,
+                head => head
+            };
+            while start < digits.len(){
+                let chunk: u32 = digits[start..end].parse().ok()?;
+                limbs = add_mag(&mul_mag(&limbs, &[10u32.pow((end - start) as u32)]), &[chunk]);
+                start = end;
+                end += DECIMAL_DIGITS;
+            }
+            Some(BigInt::from_parts(negative, limbs))
+        }
+
+        pub fn is_zero(&self) -> bool{
+            self.limbs.is_empty()
+        }
+
+        pub fn is_negative(&self) -> bool{
+            self.negative
+        }
+
+        pub fn is_one(&self) -> bool{
+            !self.negative && self.limbs == [1]
+        }
+
+        pub fn is_even(&self) -> bool{
+            self.limbs.first().is_none_or(|l| l % 2 == 0)
+        }
+
+        pub fn abs(&self) -> BigInt{
+            BigInt{negative: false, limbs: self.limbs.clone()}
+        }
+
+        // Number of significant bits of the magnitude.
+        pub fn bits(&self) -> u64{
+            match self.limbs.last(){
+                Some(top) => self.limbs.len() as u64 * 32 - top.leading_zeros() as u64,
+                None => 0
+            }
+        }
+
+        pub fn shifted_left(&self, bits: u64) -> BigInt{
+            BigInt::from_parts(self.negative, shl_mag(&self.limbs, bits))
+        }
+
+        // Shifts the magnitude, rounding toward zero.
+        pub fn shifted_right(&self, bits: u64) -> BigInt{
+            BigInt::from_parts(self.negative, shr_mag(&self.limbs, bits))
+        }
+
+        // Truncating division like i64, the remainder takes the sign of self.
+        pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)>{
+            if other.is_zero(){
+                return None;
+            }
+            let (q, r) = divrem_mag(&self.limbs, &other.limbs);
+            Some((BigInt::from_parts(self.negative != other.negative, q), BigInt::from_parts(self.negative, r)))
+        }
+
+        // Division rounding toward negative infinity.
+        pub fn div_floor(&self, other: &BigInt) -> Option<BigInt>{
+            let (q, r) = self.div_rem(other)?;
+            if !r.is_zero() && r.negative != other.negative{
+                return Some(&q - &BigInt::one());
+            }
+            Some(q)
+        }
+
+        pub fn gcd(&self, other: &BigInt) -> BigInt{
+            let (mut a, mut b) = (self.abs(), other.abs());
+            while !b.is_zero(){
+                let r = divrem_mag(&a.limbs, &b.limbs).1;
+                a = b;
+                b = BigInt::from_parts(false, r);
+            }
+            a
+        }
+
+        pub fn pow(&self, mut exp: u64) -> BigInt{
+            let mut base = self.clone();
+            let mut out = BigInt::one();
+            while exp > 0{
+                if exp % 2 == 1{
+                    out = &out * &base;
+                }
+                exp /= 2;
+                if exp > 0{
+                    base = &base * &base;
+                }
+            }
+            out
+        }
+
+        pub fn factorial(n: u64) -> Result<BigInt, EvalError>{
+            if n > MAX_FACTORIAL{
+                return Err(EvalError::Overflow);
+            }
+            let mut out = BigInt::one();
+            for i in 2..=n{
+                out = &out * &BigInt::from(i);
+            }
+            Ok(out)
+        }
+
+        // Each partial product is itself a binomial coefficient, so every division is exact.
+        pub fn binomial(n: u64, k: u64) -> Result<BigInt, EvalError>{
+            if k > n{
+                return Ok(BigInt::zero());
+            }
+            // The loop runs min(k, n - k) times, so only that is bounded.
+            let k = k.min(n - k);
+            if k > MAX_FACTORIAL{
+                return Err(EvalError::Overflow);
+            }
+            let mut out = BigInt::one();
+            for i in 1..=k{
+                let (q, _) = divrem_small(&mul_mag(&out.limbs, &BigInt::from(n - k + i).limbs), i as u32);
+                out = BigInt::from_parts(false, q);
+            }
+            Ok(out)
+        }
+
+        pub fn to_u64(&self) -> Option<u64>{
+            if self.negative || self.limbs.len() > 2{
+                return None;
+            }
+            Some(self.limbs.iter().rev().fold(0u64, |acc, &l| (acc << 32) | l as u64))
+        }
+
+        pub fn to_i64(&self) -> Option<i64>{
+            if self.limbs.len() > 2{
+                return None;
+            }
+            let mag = self.limbs.iter().rev().fold(0u64, |acc, &l| (acc << 32) | l as u64);
+            if self.negative{
+                0i64.checked_sub_unsigned(mag)
+            }else{
+                i64::try_from(mag).ok()
+            }
+        }
+
+        // Rounds using the top 96 bits, far beyond f64 precision.
+        pub fn to_f64(&self) -> f64{
+            let len = self.limbs.len();
+            let top = self.limbs.iter().rev().take(3).fold(0u128, |acc, &l| (acc << 32) | l as u128);
+            let exp = 32 * len.saturating_sub(3) as i32;
+            let mag = scale(top as f64, exp);
+            if self.negative {-mag} else {mag}
+        }
+    }
+
+    // Division has to come out even, a remainder is NotExact.
+    impl Number for BigInt{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            if !src.bytes().all(|b| b.is_ascii_digit()){
+                return Err("Bad integer");
+            }
+            BigInt::parse(src).ok_or("Bad integer")
+        }
+
+        fn zero() -> Self{
+            BigInt::zero()
+        }
+
+        fn one() -> Self{
+            BigInt::one()
+        }
+
+        fn to_f64(&self) -> f64{
+            BigInt::to_f64(self)
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self + other)
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self - other)
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self * other)
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            match self.div_rem(other){
+                Some((q, r)) if r.is_zero() => Ok(q),
+                Some(_) => Err(EvalError::NotExact),
+                None => Err(EvalError::DivisionByZero)
+            }
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            if other.is_negative(){
+                return Err(EvalError::NotExact);
+            }
+            let exp = other.to_u64().ok_or(EvalError::Overflow)?;
+            if self.bits() > 1 && self.bits().saturating_mul(exp) > MAX_BITS{
+                return Err(EvalError::Overflow);
+            }
+            Ok(BigInt::pow(self, exp))
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            match func{
+                Function::Neg => Ok(-self),
+                Function::Abs => Ok(self.abs()),
+                Function::Fact => BigInt::factorial(self.to_u64().ok_or(EvalError::NotExact)?),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            match (func, self.to_u64(), other.to_u64()){
+                (Function::Binom, Some(n), Some(k)) => BigInt::binomial(n, k),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+    }
+
+    // x * 2^exp without overflowing the intermediate power of two.
+    pub fn scale(mut x: f64, mut exp: i32) -> f64{
+        while exp > 1000{
+            x *= 2f64.powi(1000);
+            exp -= 1000;
+        }
+        while exp < -1000{
+            x *= 2f64.powi(-1000);
+            exp += 1000;
+        }
+        x * 2f64.powi(exp)
+    }
+
+    impl From<u64> for BigInt{
+        fn from(num: u64) -> Self{
+            BigInt::from_parts(false, vec![num as u32, (num >> 32) as u32])
+        }
+    }
+
+    impl From<i64> for BigInt{
+        fn from(num: i64) -> Self{
+            let mag = BigInt::from(num.unsigned_abs());
+            BigInt::from_parts(num < 0, mag.limbs)
+        }
+    }
+
+    impl Ord for BigInt{
+        fn cmp(&self, other: &Self) -> Ordering{
+            match (self.negative, other.negative){
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
+                (false, false) => cmp_mag(&self.limbs, &other.limbs),
+                (true, true) => cmp_mag(&other.limbs, &self.limbs)
+            }
+        }
+    }
+
+    impl PartialOrd for BigInt{
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering>{
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Add for &BigInt{
+        type Output = BigInt;
+
+        fn add(self, other: &BigInt) -> BigInt{
+            if self.negative == other.negative{
+                return BigInt::from_parts(self.negative, add_mag(&self.limbs, &other.limbs));
+            }
+            match cmp_mag(&self.limbs, &other.limbs){
+                Ordering::Less => BigInt::from_parts(other.negative, sub_mag(&other.limbs, &self.limbs)),
+                _ => BigInt::from_parts(self.negative, sub_mag(&self.limbs, &other.limbs))
+            }
+        }
+    }
+
+    impl Sub for &BigInt{
+        type Output = BigInt;
+
+        fn sub(self, other: &BigInt) -> BigInt{
+            self + &(-other)
+        }
+    }
+
+    impl Mul for &BigInt{
+        type Output = BigInt;
+
+        fn mul(self, other: &BigInt) -> BigInt{
+            BigInt::from_parts(self.negative != other.negative, mul_mag(&self.limbs, &other.limbs))
+        }
+    }
+
+    impl Neg for &BigInt{
+        type Output = BigInt;
+
+        fn neg(self) -> BigInt{
+            BigInt::from_parts(!self.negative, self.limbs.clone())
+        }
+    }
+
+    impl fmt::Display for BigInt{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            if self.is_zero(){
+                return write!(f, "0");
+            }
+            let mut chunks = Vec::new();
+            let mut rest = self.limbs.clone();
+            while !rest.is_empty(){
+                let (q, r) = divrem_small(&rest, DECIMAL_BASE);
+                chunks.push(r);
+                rest = q;
+            }
+            let mut out = String::new();
+            for (i, chunk) in chunks.iter().rev().enumerate(){
+                if i == 0{
+                    out.push_str(&chunk.to_string());
+                }else{
+                    out.push_str(&format!("{:09}", chunk));
+                }
+            }
+            f.pad_integral(!self.negative, "", &out)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use std::collections::HashMap;
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree_with;
+
+        fn evaluate(src: &str) -> Result<String, EvalError>{
+            parse_to_tree_with(src, BigInt::from_literal).unwrap().evaluate_number(&HashMap::new()).map(|num| num.to_string())
+        }
+
+        #[test]
+        fn factorials_and_powers_are_exact(){
+            assert_eq!(evaluate("25!").unwrap(), "15511210043330985984000000");
+            assert_eq!(evaluate("2 ^ 100").unwrap(), "1267650600228229401496703205376");
+            assert_eq!(evaluate("binom(100, 50)").unwrap(), "100891344545564193334812497256");
+            assert_eq!(evaluate("binom(100000, 1)").unwrap(), "100000");
+            assert_eq!(evaluate("binom(100000, 99998)").unwrap(), "4999950000");
+            assert!(matches!(evaluate("binom(100000, 50000)"), Err(EvalError::Overflow)));
+        }
+
+        #[test]
+        fn parse_and_print_round_trip(){
+            for src in ["0", "-1", "999999999", "1000000000", "-123456789012345678901234567890"]{
+                assert_eq!(BigInt::parse(src).unwrap().to_string(), src);
+            }
+        }
+
+        #[test]
+        fn division_truncates_like_i64(){
+            let (q, r) = BigInt::from(-7i64).div_rem(&BigInt::from(2i64)).unwrap();
+            assert_eq!((q.to_i64(), r.to_i64()), (Some(-3), Some(-1)));
+            assert_eq!(BigInt::from(-7i64).div_floor(&BigInt::from(2i64)).unwrap().to_i64(), Some(-4));
+        }
+
+        #[test]
+        fn inexact_and_zero_division_are_errors(){
+            assert!(matches!(evaluate("7 / 2"), Err(EvalError::NotExact)));
+            assert!(matches!(evaluate("7 / (2 - 2)"), Err(EvalError::DivisionByZero)));
+            assert!(matches!(evaluate("2 ^ -1"), Err(EvalError::NotExact)));
+        }
+    }
+}
@@ -0,0 +1,327 @@
+#[allow(dead_code)]
+pub mod bytecode{
+    use std::{
+        fmt,
+        time
+    };
+    use crate::calculator_model::model::{
+        Env,
+        EvalError,
+        Function,
+        Node,
+        Operator,
+        Tree,
+        TreeError,
+        Value
+    };
+
+    #[derive(Clone, Debug)]
+    pub enum Instruction{
+        Push(f64),
+        Load(usize),
+        Binary(Operator),
+        Call(Function)
+    }
+
+    impl fmt::Display for Instruction{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            match self{
+                Instruction::Push(num) => write!(f,"push {}",num),
+                Instruction::Load(slot) => write!(f,"load {}",slot),
+                Instruction::Binary(op) => write!(f,"op {}",op),
+                Instruction::Call(func) => write!(f,"call {}",func)
+            }
+        }
+    }
+
+    // Postfix program for a Tree<Value>, variables are resolved to slots at compile time.
+    pub struct Program{
+        instructions: Vec<Instruction>,
+        variables: Vec<String>,
+        stack_size: usize
+    }
+
+    pub fn compile(tree: &Tree<Value>) -> Result<Program, EvalError>{
+        match tree.root_node(){
+            Some(root) => {
+                let mut program = Program{
+                    instructions: Vec::new(),
+                    variables: Vec::new(),
+                    stack_size: 0
+                };
+                program.stack_size = program.emit(root)?;
+                Ok(program)
+            },
+            None => Err(EvalError::BadTree)
+        }
+    }
+
+    impl Program{
+        // Returns the stack depth needed to evaluate the subtree.
+        fn emit(&mut self, node: &Node<Value>) -> Result<usize, EvalError>{
+            match &node.value{
+                Some(Value::Value(num)) => {
+                    self.instructions.push(Instruction::Push(*num));
+                    Ok(1)
+                },
+                Some(Value::Variable(name)) => {
+                    let slot = self.slot(name);
+                    self.instructions.push(Instruction::Load(slot));
+                    Ok(1)
+                },
+                Some(Value::Operator(op)) => {
+                    let left = self.emit_operand(node.left_node())?;
+                    let right = self.emit_operand(node.right_node())?;
+                    self.instructions.push(Instruction::Binary(*op));
+                    Ok(left.max(right + 1))
+                },
+                Some(Value::Function(func)) if func.arity() == 2 => {
+                    let left = self.emit_operand(node.left_node())?;
+                    let right = self.emit_operand(node.right_node())?;
+                    self.instructions.push(Instruction::Call(*func));
+                    Ok(left.max(right + 1))
+                },
+                Some(Value::Function(func)) => {
+                    let arg = match node.left_node(){
+                        Some(arg) => self.emit(arg)?,
+                        None => return Err(EvalError::BadTree)
+                    };
+                    self.instructions.push(Instruction::Call(*func));
+                    Ok(arg)
+                },
+                // Bodies of integrate, sum and product are only run by rec_evaluate.
+                Some(Value::Aggregate(_)) | Some(Value::Range(_)) => Err(EvalError::UnexpectedOp),
+                None => Err(EvalError::BadTree)
+            }
+        }
+
+        // A missing operand counts as 0, the same as rec_evaluate.
+        fn emit_operand(&mut self, node: Option<&Node<Value>>) -> Result<usize, EvalError>{
+            match node{
+                Some(n) => self.emit(n),
+                None => {
+                    self.instructions.push(Instruction::Push(0f64));
+                    Ok(1)
+                }
+            }
+        }
+
+        fn slot(&mut self, name: &str) -> usize{
+            match self.variables.iter().position(|v| v == name){
+                Some(slot) => slot,
+                None => {
+                    self.variables.push(name.to_string());
+                    self.variables.len() - 1
+                }
+            }
+        }
+
+        pub fn instructions(&self) -> &[Instruction]{
+            &self.instructions
+        }
+
+        pub fn variables(&self) -> &[String]{
+            &self.variables
+        }
+
+        pub fn slot_of(&self, name: &str) -> Option<usize>{
+            self.variables.iter().position(|v| v == name)
+        }
+
+        pub fn run(&self, vars: &[f64]) -> Result<f64, EvalError>{
+            if vars.len() < self.variables.len(){
+                return Err(EvalError::UnknownVariable(self.variables[vars.len()].clone()));
+            }
+            let mut stack: Vec<f64> = Vec::with_capacity(self.stack_size);
+            for instruction in &self.instructions{
+                match instruction{
+                    Instruction::Push(num) => stack.push(*num),
+                    Instruction::Load(slot) => stack.push(vars[*slot]),
+                    Instruction::Binary(op) => {
+                        match (stack.pop(), stack.pop()){
+                            (Some(right), Some(left)) => stack.push(op.evaluate(left, right)),
+                            _ => return Err(EvalError::BadTree)
+                        }
+                    },
+                    Instruction::Call(func) if func.arity() == 2 => {
+                        match (stack.pop(), stack.pop()){
+                            (Some(right), Some(left)) => stack.push(func.evaluate_binary(left, right)),
+                            _ => return Err(EvalError::BadTree)
+                        }
+                    },
+                    Instruction::Call(func) => {
+                        match stack.pop(){
+                            Some(arg) => stack.push(func.evaluate(arg)),
+                            None => return Err(EvalError::BadTree)
+                        }
+                    }
+                }
+            }
+            match (stack.pop(), stack.is_empty()){
+                (Some(num), true) => Ok(num),
+                _ => Err(EvalError::BadTree)
+            }
+        }
+
+        pub fn run_env(&self, env: &Env) -> Result<f64, EvalError>{
+            let mut vars = Vec::with_capacity(self.variables.len());
+            for name in &self.variables{
+                match env.get(name){
+                    Some(num) => vars.push(*num),
+                    None => return Err(EvalError::UnknownVariable(name.clone()))
+                }
+            }
+            self.run(&vars)
+        }
+    }
+
+    impl fmt::Display for Program{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            for (i, name) in self.variables.iter().enumerate(){
+                writeln!(f,"slot {}: {}",i,name)?;
+            }
+            for instruction in &self.instructions{
+                writeln!(f,"{}",instruction)?;
+            }
+            Ok(())
+        }
+    }
+
+    const RUNS: usize = 100_000;
+
+    pub fn bench() -> Result<(), TreeError>{
+        let mut tree:Tree<Value> = Tree::new();
+        tree.set_current(Value::Operator(Operator::Plus));
+        tree.set_child_right(Value::Operator(Operator::Plus))?;
+        tree.set_child_left(Value::Value(2f64))?;
+        for _ in 0..10{
+            tree.go_right()?;
+            tree.set_child_left(Value::Value(2f64))?;
+            tree.set_child_right(Value::Operator(Operator::Plus))?;
+        }
+        tree.set_child_right(Value::Value(2f64))?;
+
+        let program = match compile(&tree){
+            Ok(p) => p,
+            Err(e) => {
+                println!("{}",e);
+                return Ok(());
+            }
+        };
+
+        let start = time::Instant::now();
+        let mut sum = 0f64;
+        for _ in 0..RUNS{
+            sum += tree.rec_evaluate().unwrap_or(f64::NAN);
+        }
+        println!("rec_evaluate {}, elapsed time in ns {}",sum, start.elapsed().as_nanos());
+
+        let start = time::Instant::now();
+        let mut sum = 0f64;
+        for _ in 0..RUNS{
+            sum += tree.evaluate_it3().unwrap_or(f64::NAN);
+        }
+        println!("evaluate_it3 {}, elapsed time in ns {}",sum, start.elapsed().as_nanos());
+
+        let start = time::Instant::now();
+        let mut sum = 0f64;
+        for _ in 0..RUNS{
+            sum += program.run(&[]).unwrap_or(f64::NAN);
+        }
+        println!("bytecode {}, elapsed time in ns {}",sum, start.elapsed().as_nanos());
+
+        // x*x + (3*x - y/2)
+        let mut tree:Tree<Value> = Tree::new();
+        tree.set_current(Value::Operator(Operator::Plus));
+        tree.set_child_left(Value::Operator(Operator::Mult))?;
+        tree.set_child_right(Value::Operator(Operator::Minus))?;
+        tree.go_left()?;
+        tree.set_child_left(Value::new_var("x"))?;
+        tree.set_child_right(Value::new_var("x"))?;
+        tree.go_up()?;
+        tree.go_right()?;
+        tree.set_child_left(Value::Operator(Operator::Mult))?;
+        tree.set_child_right(Value::Operator(Operator::Div))?;
+        tree.go_left()?;
+        tree.set_child_left(Value::Value(3f64))?;
+        tree.set_child_right(Value::new_var("x"))?;
+        tree.go_up()?;
+        tree.go_right()?;
+        tree.set_child_left(Value::new_var("y"))?;
+        tree.set_child_right(Value::Value(2f64))?;
+
+        let program = match compile(&tree){
+            Ok(p) => p,
+            Err(e) => {
+                println!("{}",e);
+                return Ok(());
+            }
+        };
+        let (x_slot, y_slot) = match (program.slot_of("x"), program.slot_of("y")){
+            (Some(x), Some(y)) => (x, y),
+            _ => return Ok(())
+        };
+
+        let start = time::Instant::now();
+        let mut sum = 0f64;
+        let mut env = Env::new();
+        for i in 0..RUNS{
+            env.insert("x".to_string(), i as f64);
+            env.insert("y".to_string(), (RUNS - i) as f64);
+            sum += tree.rec_evaluate_env(&env).unwrap_or(f64::NAN);
+        }
+        println!("rec_evaluate_env {}, elapsed time in ns {}",sum, start.elapsed().as_nanos());
+
+        let start = time::Instant::now();
+        let mut sum = 0f64;
+        let mut vars = [0f64;2];
+        for i in 0..RUNS{
+            vars[x_slot] = i as f64;
+            vars[y_slot] = (RUNS - i) as f64;
+            sum += program.run(&vars).unwrap_or(f64::NAN);
+        }
+        println!("bytecode {}, elapsed time in ns {}",sum, start.elapsed().as_nanos());
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree;
+
+        #[test]
+        fn programs_match_the_tree(){
+            let env = Env::from([("x".to_string(), 1.5f64), ("y".to_string(), -4f64)]);
+            for src in ["1 + 2 * 3", "x * x + (3 * x - y / 2)", "sin(x) ^ 2 + cos(x) ^ 2", "binom(10, 3) - 5!", "-y / 3"]{
+                let mut tree = parse_to_tree(src).unwrap();
+                let program = compile(&tree).unwrap();
+                assert_eq!(program.run_env(&env).unwrap(), tree.rec_evaluate_env(&env).unwrap(), "{}", src);
+            }
+        }
+
+        #[test]
+        fn variables_get_one_slot_each(){
+            let program = compile(&parse_to_tree("y * x + x / y").unwrap()).unwrap();
+            assert_eq!(program.variables(), ["y".to_string(), "x".to_string()]);
+            assert_eq!(program.slot_of("x"), Some(1));
+            assert_eq!(program.slot_of("z"), None);
+            assert_eq!(program.run(&[2f64, 3f64]).unwrap(), 7.5);
+            assert!(matches!(program.run(&[2f64]), Err(EvalError::UnknownVariable(name)) if name == "x"));
+            assert!(matches!(program.run_env(&Env::new()), Err(EvalError::UnknownVariable(name)) if name == "y"));
+        }
+
+        #[test]
+        fn stack_size_follows_the_deepest_operand(){
+            assert_eq!(compile(&parse_to_tree("1 + (2 + (3 + 4))").unwrap()).unwrap().stack_size, 4);
+            assert_eq!(compile(&parse_to_tree("((1 + 2) + 3) + 4").unwrap()).unwrap().stack_size, 2);
+        }
+
+        #[test]
+        fn aggregates_are_not_compiled(){
+            let tree = parse_to_tree("sum(k, k, 1, 10)").unwrap();
+            assert!(matches!(compile(&tree), Err(EvalError::UnexpectedOp)));
+            assert!(matches!(compile(&Tree::new()), Err(EvalError::BadTree)));
+        }
+    }
+}
@@ -0,0 +1,304 @@
+#[allow(dead_code)]
+pub mod calculus{
+    use std::{
+        collections::HashMap,
+        fmt
+    };
+    use crate::calculator_model::model::{
+        parse_number,
+        Aggregate,
+        EvalError,
+        Function,
+        Operator,
+        Tree,
+        Value
+    };
+    use crate::calculator_number::number::Number;
+
+    // Sums and products with more terms than this are rejected instead of looping for ages.
+    const MAX_TERMS: f64 = 1e7;
+    const MAX_INTERVALS: usize = 500;
+    const ABS_TOLERANCE: f64 = 1e-12;
+    const REL_TOLERANCE: f64 = 1e-10;
+
+    // Non-negative 15 point Kronrod nodes on [-1, 1], every other one from the end is a 7 point Gauss node.
+    const XGK: [f64; 8] = [
+        0.9914553711208126,
+        0.9491079123427585,
+        0.8648644233597691,
+        0.7415311855993945,
+        0.5860872354676911,
+        0.4058451513773972,
+        0.20778495500789848,
+        0.0
+    ];
+    const WGK: [f64; 8] = [
+        0.022935322010529224,
+        0.06309209262997856,
+        0.10479001032225019,
+        0.14065325971552592,
+        0.1690047266392679,
+        0.19035057806478542,
+        0.20443294007529889,
+        0.20948214108472782
+    ];
+    const WG: [f64; 4] = [
+        0.1294849661688697,
+        0.27970539148927664,
+        0.3818300505051189,
+        0.4179591836734694
+    ];
+
+    // A value with the error estimate of the integrals it came from, carried through whatever is done with it.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Quadrature{
+        pub value: f64,
+        pub error: f64
+    }
+
+    impl Quadrature{
+        pub fn exact(value: f64) -> Quadrature{
+            Quadrature{value, error: 0f64}
+        }
+
+        // Moves each operand by its error both ways and keeps the furthest the result goes.
+        fn spread<F: Fn(f64, f64) -> f64>(&self, other: &Quadrature, f: F) -> Quadrature{
+            let value = f(self.value, other.value);
+            let mut error = 0f64;
+            if self.error > 0f64 || other.error > 0f64{
+                for a in [self.value - self.error, self.value + self.error]{
+                    for b in [other.value - other.error, other.value + other.error]{
+                        error = error.max((f(a, b) - value).abs());
+                    }
+                }
+            }
+            Quadrature{value, error}
+        }
+    }
+
+    impl fmt::Display for Quadrature{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            write!(f, "{}", self.value)
+        }
+    }
+
+    impl Number for Quadrature{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            Ok(Quadrature::exact(parse_number(src)?))
+        }
+
+        fn zero() -> Self{
+            Quadrature::exact(0f64)
+        }
+
+        fn one() -> Self{
+            Quadrature::exact(1f64)
+        }
+
+        fn to_f64(&self) -> f64{
+            self.value
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.spread(other, |a, b| a + b))
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.spread(other, |a, b| a - b))
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.spread(other, |a, b| a * b))
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.spread(other, |a, b| a / b))
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.spread(other, f64::powf))
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            Ok(self.spread(&Quadrature::zero(), |a, _| func.evaluate(a)))
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.spread(other, |a, b| func.evaluate_binary(a, b)))
+        }
+
+        fn bitwise(&self, op: Operator, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.spread(other, |a, b| op.evaluate(a, b)))
+        }
+
+        // Errors of the terms add up in a sum, relative to each term in a product and over the width in an integral.
+        // The bounds are taken as exact.
+        fn aggregate<F: FnMut(&Self) -> Result<Self, EvalError>>(agg: Aggregate, from: &Self, to: &Self, mut body: F) -> Result<Self, EvalError>{
+            let (mut absolute, mut relative, mut largest) = (0f64, 0f64, 0f64);
+            let total = agg.evaluate(from.value, to.value, |x| {
+                let term = body(&Quadrature::exact(x))?;
+                if term.error > 0f64{
+                    absolute += term.error;
+                    relative += term.error / term.value.abs();
+                    largest = term.error.max(largest);
+                }
+                Ok(term.value)
+            })?;
+            let carried = match agg{
+                Aggregate::Sum => absolute,
+                Aggregate::Product => relative * total.value.abs(),
+                Aggregate::Integrate => largest * (to.value - from.value).abs()
+            };
+            Ok(Quadrature{value: total.value, error: total.error + carried})
+        }
+    }
+
+    impl Tree<Value>{
+        // Like rec_evaluate, with the error estimate of any integral on the way carried to the result.
+        pub fn evaluate_estimate(&self) -> Result<Quadrature, EvalError>{
+            match self.root_node(){
+                Some(root) => {
+                    let root = root.try_map(&|num: &f64| Ok::<_, EvalError>(Quadrature::exact(*num)))?;
+                    root.evaluate_number(&HashMap::new())
+                },
+                None => Err(EvalError::BadTree)
+            }
+        }
+    }
+
+    struct Interval{
+        a: f64,
+        b: f64,
+        value: f64,
+        error: f64
+    }
+
+    // Kronrod estimate over [a, b], the error is its distance to the embedded Gauss estimate.
+    fn kronrod<F: FnMut(f64) -> Result<f64, EvalError>>(f: &mut F, a: f64, b: f64) -> Result<Interval, EvalError>{
+        let center = (a + b) / 2f64;
+        let half = (b - a) / 2f64;
+        let fc = f(center)?;
+        let mut kronrod = fc * WGK[7];
+        let mut gauss = fc * WG[3];
+        for j in 0..7{
+            let dx = half * XGK[j];
+            let pair = f(center - dx)? + f(center + dx)?;
+            kronrod += WGK[j] * pair;
+            if j % 2 == 1{
+                gauss += WG[j / 2] * pair;
+            }
+        }
+        Ok(Interval{
+            a,
+            b,
+            value: kronrod * half,
+            error: ((kronrod - gauss) * half).abs()
+        })
+    }
+
+    // Adaptive Gauss-Kronrod, always splits the interval with the largest error estimate.
+    // Running out of intervals before the tolerance is met is an error carrying the estimate reached.
+    pub fn integrate<F: FnMut(f64) -> Result<f64, EvalError>>(mut f: F, a: f64, b: f64) -> Result<Quadrature, EvalError>{
+        if !a.is_finite() || !b.is_finite(){
+            return Err(EvalError::BadRange);
+        }
+        let mut intervals = vec![kronrod(&mut f, a, b)?];
+        loop{
+            let value: f64 = intervals.iter().map(|i| i.value).sum();
+            let error: f64 = intervals.iter().map(|i| i.error).sum();
+            if !value.is_finite() || error <= ABS_TOLERANCE.max(REL_TOLERANCE * value.abs()){
+                return Ok(Quadrature{value, error});
+            }
+            if intervals.len() >= MAX_INTERVALS{
+                return Err(EvalError::NotConverged(error));
+            }
+            let worst = intervals.iter().enumerate()
+                .fold(0, |worst, (i, interval)| if interval.error > intervals[worst].error {i} else {worst});
+            let Interval{a, b, ..} = intervals.swap_remove(worst);
+            let mid = (a + b) / 2f64;
+            intervals.push(kronrod(&mut f, a, mid)?);
+            intervals.push(kronrod(&mut f, mid, b)?);
+        }
+    }
+
+    // Number of unit steps from `from` that stay within `to`.
+    pub fn terms(from: f64, to: f64) -> Result<u64, EvalError>{
+        if !from.is_finite() || !to.is_finite(){
+            return Err(EvalError::BadRange);
+        }
+        if to < from{
+            return Ok(0);
+        }
+        let count = (to - from).floor() + 1f64;
+        if count > MAX_TERMS{
+            return Err(EvalError::BadRange);
+        }
+        Ok(count as u64)
+    }
+
+    // Steps from `from` by 1 while not past `to`, an empty range sums to 0.
+    pub fn sum<F: FnMut(f64) -> Result<f64, EvalError>>(mut f: F, from: f64, to: f64) -> Result<f64, EvalError>{
+        let mut total = 0f64;
+        for k in 0..terms(from, to)?{
+            total += f(from + k as f64)?;
+        }
+        Ok(total)
+    }
+
+    pub fn product<F: FnMut(f64) -> Result<f64, EvalError>>(mut f: F, from: f64, to: f64) -> Result<f64, EvalError>{
+        let mut total = 1f64;
+        for k in 0..terms(from, to)?{
+            total *= f(from + k as f64)?;
+        }
+        Ok(total)
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use std::f64::consts::PI;
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree;
+
+        #[test]
+        fn kronrod_is_exact_on_polynomials(){
+            let quad = integrate(|x| Ok(x.powi(20) - 3f64 * x), -1f64, 2f64).unwrap();
+            let exact = (2f64.powi(21) + 1f64) / 21f64 - 4.5;
+            assert!((quad.value - exact).abs() < 1e-9 * exact);
+            assert!(quad.error <= 1e-10 * exact);
+        }
+
+        #[test]
+        fn adapts_to_hard_integrands(){
+            let quad = integrate(|x| Ok(x.sin()), 0f64, PI).unwrap();
+            assert!((quad.value - 2f64).abs() < 1e-12);
+            let quad = integrate(|x| Ok(1f64 / x.sqrt()), 0f64, 1f64).unwrap();
+            assert!((quad.value - 2f64).abs() <= quad.error.max(1e-9));
+            assert!(quad.error > 0f64);
+        }
+
+        #[test]
+        fn running_out_of_intervals_is_an_error(){
+            assert!(matches!(integrate(|x| Ok(1f64 / x), 0f64, 1f64), Err(EvalError::NotConverged(_))));
+            assert!(matches!(integrate(Ok, 0f64, f64::INFINITY), Err(EvalError::BadRange)));
+        }
+
+        #[test]
+        fn estimates_travel_with_the_result(){
+            let estimate = |src: &str| parse_to_tree(src).unwrap().evaluate_estimate().unwrap();
+            let single = estimate("integrate(1/sqrt(x), x, 0, 1)");
+            let doubled = estimate("2 * integrate(1/sqrt(x), x, 0, 1) + 1");
+            assert!(single.error > 0f64);
+            assert!((doubled.error / single.error - 2f64).abs() < 1e-6);
+            assert_eq!(estimate("sum(k, k, 1, 4)"), Quadrature::exact(10f64));
+        }
+
+        #[test]
+        fn sums_and_products_count_unit_steps(){
+            assert_eq!(terms(1f64, 3.5).unwrap(), 3);
+            assert_eq!(terms(2f64, 1f64).unwrap(), 0);
+            assert_eq!(sum(|k| Ok(k * k), 1f64, 3f64).unwrap(), 14f64);
+            assert_eq!(product(Ok, 1f64, 5f64).unwrap(), 120f64);
+            assert!(matches!(terms(0f64, 1e9), Err(EvalError::BadRange)));
+        }
+    }
+}
@@ -0,0 +1,319 @@
+#[allow(dead_code)]
+pub mod complex{
+    use std::{
+        collections::HashMap,
+        fmt
+    };
+    use crate::calculator_calculus::calculus;
+    use crate::calculator_model::model::{
+        parse_number,
+        parse_to_tree_with,
+        Aggregate,
+        EvalError,
+        Function,
+        Tree,
+        Value
+    };
+    use crate::calculator_number::number::Number;
+
+    // Integer powers up to this size are multiplied out so i^2 is exactly -1.
+    const MAX_EXACT_POWER: f64 = 1024f64;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Complex{
+        pub re: f64,
+        pub im: f64
+    }
+
+    impl Complex{
+        pub fn new(re: f64, im: f64) -> Complex{
+            Complex{re, im}
+        }
+
+        pub fn real(re: f64) -> Complex{
+            Complex{re, im: 0f64}
+        }
+
+        pub fn i() -> Complex{
+            Complex{re: 0f64, im: 1f64}
+        }
+
+        // Either "2.5" or an imaginary literal "2.5i".
+        pub fn parse_literal(src: &str) -> Result<Complex, &'static str>{
+            match src.strip_suffix('i'){
+                Some(im) => Ok(Complex::new(0f64, parse_number(im)?)),
+                None => Ok(Complex::real(parse_number(src)?))
+            }
+        }
+
+        pub fn is_real(&self) -> bool{
+            self.im == 0f64
+        }
+
+        pub fn abs(&self) -> f64{
+            self.re.hypot(self.im)
+        }
+
+        pub fn arg(&self) -> f64{
+            self.im.atan2(self.re)
+        }
+
+        // Subtracting from zero keeps -0 out of the parts, so ln(-1) lands on +pi.
+        pub fn conj(&self) -> Complex{
+            Complex::new(self.re, 0f64 - self.im)
+        }
+
+        pub fn neg(&self) -> Complex{
+            Complex::new(0f64 - self.re, 0f64 - self.im)
+        }
+
+        pub fn add(&self, other: &Complex) -> Complex{
+            Complex::new(self.re + other.re, self.im + other.im)
+        }
+
+        pub fn sub(&self, other: &Complex) -> Complex{
+            Complex::new(self.re - other.re, self.im - other.im)
+        }
+
+        pub fn mul(&self, other: &Complex) -> Complex{
+            Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+        }
+
+        pub fn div(&self, other: &Complex) -> Complex{
+            if other.is_real(){
+                return Complex::new(self.re / other.re, self.im / other.re);
+            }
+            let den = other.re * other.re + other.im * other.im;
+            Complex::new((self.re * other.re + self.im * other.im) / den, (self.im * other.re - self.re * other.im) / den)
+        }
+
+        pub fn exp(&self) -> Complex{
+            let scale = self.re.exp();
+            if self.im == 0f64{
+                return Complex::real(scale);
+            }
+            Complex::new(scale * self.im.cos(), scale * self.im.sin())
+        }
+
+        // Principal branch, the cut runs along the negative real axis.
+        pub fn ln(&self) -> Complex{
+            Complex::new(self.abs().ln(), self.arg())
+        }
+
+        pub fn sqrt(&self) -> Complex{
+            let r = self.abs();
+            let re = ((r + self.re) / 2f64).sqrt();
+            let im = ((r - self.re) / 2f64).sqrt();
+            Complex::new(re, if self.im < 0f64 {-im} else {im})
+        }
+
+        pub fn sin(&self) -> Complex{
+            Complex::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+        }
+
+        pub fn cos(&self) -> Complex{
+            Complex::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+        }
+
+        // Principal value exp(w ln z), small integer exponents by repeated squaring.
+        // Zero to a power with a negative real part divides by zero.
+        pub fn pow(&self, exp: &Complex) -> Result<Complex, EvalError>{
+            if exp.is_real() && exp.re.fract() == 0f64 && exp.re.abs() <= MAX_EXACT_POWER{
+                let mut n = exp.re.abs() as u64;
+                let mut base = *self;
+                let mut out = Complex::real(1f64);
+                while n > 0{
+                    if n & 1 == 1{
+                        out = out.mul(&base);
+                    }
+                    base = base.mul(&base);
+                    n >>= 1;
+                }
+                return if exp.re < 0f64 {Number::div(&Complex::real(1f64), &out)} else {Ok(out)};
+            }
+            if self.re == 0f64 && self.im == 0f64{
+                return match exp.re{
+                    re if re > 0f64 => Ok(Complex::real(0f64)),
+                    re if re < 0f64 => Err(EvalError::DivisionByZero),
+                    _ => Ok(Complex::new(f64::NAN, f64::NAN))
+                };
+            }
+            if self.is_real() && self.re > 0f64 && exp.is_real(){
+                return Ok(Complex::real(self.re.powf(exp.re)));
+            }
+            Ok(exp.mul(&self.ln()).exp())
+        }
+
+        pub fn evaluate(&self, func: Function) -> Complex{
+            match func{
+                Function::Sin => self.sin(),
+                Function::Cos => self.cos(),
+                Function::Tan => self.sin().div(&self.cos()),
+                Function::Exp => self.exp(),
+                Function::Ln => self.ln(),
+                Function::Sqrt => self.sqrt(),
+                Function::Abs => Complex::real(self.abs()),
+                Function::Neg => self.neg(),
+                Function::Re => Complex::real(self.re),
+                Function::Im => Complex::real(self.im),
+                Function::Conj => self.conj(),
+                Function::Arg => Complex::real(self.arg()),
+                Function::Fact | Function::Binom | Function::Not | Function::Popcount | Function::Rotl | Function::Rotr => {
+                    if self.is_real() {Complex::real(func.evaluate(self.re))} else {Complex::new(f64::NAN, f64::NAN)}
+                }
+            }
+        }
+    }
+
+    // a + bi with the zero part left out, i alone for a unit imaginary part.
+    impl fmt::Display for Complex{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            let im = if self.im.abs() == 1f64 {String::new()} else {self.im.abs().to_string()};
+            if self.im == 0f64 || self.im.is_nan() && self.re.is_nan(){
+                write!(f, "{}", self.re)
+            }else if self.re == 0f64{
+                write!(f, "{}{}i", if self.im < 0f64 {"-"} else {""}, im)
+            }else{
+                write!(f, "{} {} {}i", self.re, if self.im < 0f64 {"-"} else {"+"}, im)
+            }
+        }
+    }
+
+    impl Number for Complex{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            Complex::parse_literal(src)
+        }
+
+        fn zero() -> Self{
+            Complex::real(0f64)
+        }
+
+        fn one() -> Self{
+            Complex::real(1f64)
+        }
+
+        // Only real values have a place on the number line.
+        fn to_f64(&self) -> f64{
+            if self.is_real() {self.re} else {f64::NAN}
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Complex::add(self, other))
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Complex::sub(self, other))
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Complex::mul(self, other))
+        }
+
+        // Without a sign to give infinity, dividing by zero has no answer.
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            if other.re == 0f64 && other.im == 0f64{
+                return Err(EvalError::DivisionByZero);
+            }
+            Ok(Complex::div(self, other))
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Complex::pow(self, other)
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            Ok(self.evaluate(func))
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            if !self.is_real() || !other.is_real(){
+                return Ok(Complex::new(f64::NAN, f64::NAN));
+            }
+            Ok(Complex::real(func.evaluate_binary(self.re, other.re)))
+        }
+
+        // Bounds must be real, integrals are taken part by part.
+        fn aggregate<F: FnMut(&Self) -> Result<Self, EvalError>>(agg: Aggregate, from: &Self, to: &Self, mut body: F) -> Result<Self, EvalError>{
+            let (from, to) = (from.to_f64(), to.to_f64());
+            if agg == Aggregate::Integrate{
+                let re = calculus::integrate(|x| Ok(body(&Complex::real(x))?.re), from, to)?.value;
+                let im = calculus::integrate(|x| Ok(body(&Complex::real(x))?.im), from, to)?.value;
+                return Ok(Complex::new(re, im));
+            }
+            let mut total = if agg == Aggregate::Sum {Complex::real(0f64)} else {Complex::real(1f64)};
+            for k in 0..calculus::terms(from, to)?{
+                let term = body(&Complex::real(from + k as f64))?;
+                total = if agg == Aggregate::Sum {total.add(&term)} else {total.mul(&term)};
+            }
+            Ok(total)
+        }
+    }
+
+    // Literals may carry a trailing i, the bare name i is the imaginary unit.
+    pub fn parse_complex(src: &str) -> Result<Tree<Value<Complex>>, &'static str>{
+        parse_to_tree_with(src, Complex::parse_literal)
+    }
+
+    impl Tree<Value<Complex>>{
+        pub fn evaluate_complex(&self) -> Result<Complex, EvalError>{
+            self.evaluate_complex_env(&HashMap::new())
+        }
+
+        // A variable named i in env takes the place of the imaginary unit.
+        pub fn evaluate_complex_env(&self, env: &HashMap<String, Complex>) -> Result<Complex, EvalError>{
+            let mut env = env.clone();
+            env.entry("i".to_string()).or_insert_with(Complex::i);
+            self.evaluate_number(&env)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+
+        fn complex(src: &str) -> Complex{
+            parse_complex(src).unwrap().evaluate_complex().unwrap()
+        }
+
+        fn distance(a: Complex, b: Complex) -> f64{
+            a.sub(&b).abs()
+        }
+
+        #[test]
+        fn arithmetic_with_imaginary_literals(){
+            assert_eq!(complex("(1 + 2i) * (3 - 4i)"), Complex::new(11f64, 2f64));
+            assert_eq!(complex("i ^ 2"), Complex::real(-1f64));
+            assert!(distance(complex("(11 + 2i) / (3 - 4i)"), Complex::new(1f64, 2f64)) < 1e-12);
+            assert_eq!(Complex::parse_literal("2.5i"), Ok(Complex::new(0f64, 2.5)));
+            assert!(Complex::parse_literal("2.5j").is_err());
+        }
+
+        #[test]
+        fn principal_branches(){
+            assert_eq!(complex("sqrt(-4)"), Complex::new(0f64, 2f64));
+            assert_eq!(complex("sqrt(-4 - 0i)"), Complex::new(0f64, 2f64));
+            assert_eq!(complex("ln(-1)"), Complex::new(0f64, std::f64::consts::PI));
+            assert_eq!(complex("arg(-1)"), Complex::real(std::f64::consts::PI));
+            assert!(distance(complex("exp(i * 3.141592653589793)"), Complex::real(-1f64)) < 1e-15);
+            assert_eq!(complex("conj(3 - 4i) + re(2i) + im(5 + 6i)"), Complex::new(9f64, 4f64));
+        }
+
+        #[test]
+        fn poles_of_division_and_powers(){
+            let error = |src: &str| parse_complex(src).unwrap().evaluate_complex().err();
+            assert!(matches!(error("(1 + i) / (i - i)"), Some(EvalError::DivisionByZero)));
+            assert!(matches!(error("0 ^ -1"), Some(EvalError::DivisionByZero)));
+            assert!(matches!(error("(i - i) ^ (-0.5 + i)"), Some(EvalError::DivisionByZero)));
+            assert_eq!(complex("0 ^ 2"), Complex::real(0f64));
+            assert_eq!(complex("(2i) ^ -2"), Complex::real(-0.25));
+            assert!(complex("0 ^ i").re.is_nan());
+        }
+
+        #[test]
+        fn display_drops_zero_parts(){
+            assert_eq!(Complex::new(3f64, -1f64).to_string(), "3 - i");
+            assert_eq!(Complex::new(0f64, 2.5).to_string(), "2.5i");
+            assert_eq!(Complex::real(4f64).to_string(), "4");
+        }
+    }
+}
@@ -0,0 +1,152 @@
+#[allow(dead_code)]
+pub mod currency{
+    use std::{
+        collections::HashMap,
+        fs
+    };
+    use crate::calculator_json::json::{
+        self,
+        Json
+    };
+
+    // ISO 4217 style, three capital letters.
+    pub fn is_currency_code(name: &str) -> bool{
+        name.len() == 3 && name.bytes().all(|b| b.is_ascii_uppercase())
+    }
+
+    // How much of each currency one unit of the base buys, as of the timestamp.
+    pub struct RateTable{
+        pub base: String,
+        pub timestamp: String,
+        rates: HashMap<String, f64>
+    }
+
+    impl RateTable{
+        pub fn new(base: &str, timestamp: &str, rates: Vec<(String, f64)>) -> Result<RateTable, String>{
+            if !is_currency_code(base){
+                return Err(format!("Bad currency code {}", base));
+            }
+            if timestamp.trim().is_empty(){
+                return Err("Missing timestamp".to_string());
+            }
+            let mut table = HashMap::new();
+            for (code, rate) in rates{
+                if !is_currency_code(&code){
+                    return Err(format!("Bad currency code {}", code));
+                }
+                if !rate.is_finite() || rate <= 0f64{
+                    return Err(format!("Bad rate for {}", code));
+                }
+                table.insert(code, rate);
+            }
+            Ok(RateTable{base: base.to_string(), timestamp: timestamp.trim().to_string(), rates: table})
+        }
+
+        // Lines of code,rate plus timestamp,... and base,... lines, # starts a comment.
+        pub fn from_csv(src: &str) -> Result<RateTable, String>{
+            let (mut base, mut timestamp, mut rates) = (None, None, Vec::new());
+            for (n, line) in src.lines().enumerate(){
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#'){
+                    continue;
+                }
+                let (key, value) = line.split_once(',').ok_or(format!("Bad line {}", n + 1))?;
+                let (key, value) = (key.trim(), value.trim());
+                match key{
+                    "timestamp" => timestamp = Some(value),
+                    "base" => base = Some(value),
+                    "currency" => (),
+                    code => rates.push((code.to_string(), value.parse().map_err(|_| format!("Bad rate for {}", code))?))
+                }
+            }
+            RateTable::new(base.ok_or("Missing base currency")?, timestamp.ok_or("Missing timestamp")?, rates)
+        }
+
+        // {"base": "EUR", "timestamp": "...", "rates": {"USD": 1.08, ...}}
+        pub fn from_json(src: &str) -> Result<RateTable, String>{
+            let members = match json::parse_value(src).map_err(|e| e.to_string())?{
+                Json::Object(members) => members,
+                other => return Err(format!("Rate table is {}, expected an object", other.kind()))
+            };
+            let field = |key: &str| members.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            let text = |key: &str| match field(key){
+                Some(Json::String(s)) => Ok(s.as_str()),
+                Some(other) => Err(format!("\"{}\" is {}, expected a string", key, other.kind())),
+                None => Err(format!("Missing \"{}\"", key))
+            };
+            let rates = match field("rates"){
+                Some(Json::Object(rates)) => rates,
+                Some(other) => return Err(format!("\"rates\" is {}, expected an object", other.kind())),
+                None => return Err("Missing \"rates\"".to_string())
+            };
+            let mut pairs = Vec::new();
+            for (code, rate) in rates{
+                match rate{
+                    Json::Number(rate) => pairs.push((code.clone(), *rate)),
+                    _ => return Err(format!("Bad rate for {}", code))
+                }
+            }
+            RateTable::new(text("base")?, text("timestamp")?, pairs)
+        }
+
+        // JSON when the file says so, CSV otherwise.
+        pub fn load(path: &str) -> Result<RateTable, String>{
+            let src = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+            if path.ends_with(".json") || src.trim_start().starts_with('{'){
+                RateTable::from_json(&src)
+            }else{
+                RateTable::from_csv(&src)
+            }
+        }
+
+        // Worth of one unit of code in the base currency.
+        pub fn value_of(&self, code: &str) -> Option<f64>{
+            if code == self.base{
+                return Some(1f64);
+            }
+            self.rates.get(code).map(|rate| 1f64 / rate)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_units::units::UnitTable;
+
+        const CSV: &str = "# rates\nbase,EUR\ntimestamp,2024-01-01\ncurrency,rate\nUSD,2\n";
+
+        #[test]
+        fn new_checks_codes_timestamp_and_rates(){
+            assert!(RateTable::new("EUR", "2024-01-01", vec![("USD".to_string(), 1.08)]).is_ok());
+            assert_eq!(RateTable::new("eur", "2024-01-01", vec![]).err().unwrap(), "Bad currency code eur");
+            assert_eq!(RateTable::new("EUR", " ", vec![]).err().unwrap(), "Missing timestamp");
+            assert_eq!(RateTable::new("EUR", "now", vec![("USD".to_string(), 0f64)]).err().unwrap(), "Bad rate for USD");
+            assert_eq!(RateTable::new("EUR", "now", vec![("US".to_string(), 1f64)]).err().unwrap(), "Bad currency code US");
+        }
+
+        #[test]
+        fn reads_csv_and_json(){
+            let table = RateTable::from_csv(CSV).unwrap();
+            assert_eq!(table.base, "EUR");
+            assert_eq!(table.timestamp, "2024-01-01");
+            assert_eq!(table.value_of("USD"), Some(0.5));
+            assert_eq!(table.value_of("EUR"), Some(1f64));
+            assert_eq!(table.value_of("GBP"), None);
+            let table = RateTable::from_json(r#"{"base": "EUR", "timestamp": "2024-01-01", "rates": {"USD": 2}}"#).unwrap();
+            assert_eq!(table.value_of("USD"), Some(0.5));
+            assert_eq!(RateTable::from_csv("USD,2").err().unwrap(), "Missing base currency");
+            assert_eq!(RateTable::from_csv("base,EUR\nUSD").err().unwrap(), "Bad line 2");
+            assert_eq!(RateTable::from_json("[]").err().unwrap(), "Rate table is an array, expected an object");
+            assert_eq!(RateTable::from_json(r#"{"base": "EUR", "timestamp": "now"}"#).err().unwrap(), "Missing \"rates\"");
+        }
+
+        #[test]
+        fn converts_through_the_unit_table(){
+            let mut table = UnitTable::default();
+            table.set_rates(RateTable::from_csv(CSV).unwrap());
+            assert_eq!(table.evaluate_str("10 USD to EUR").unwrap(), "5 EUR (rates of 2024-01-01)");
+            assert_eq!(table.evaluate_str("10 EUR + 4 USD").unwrap(), "12 EUR (rates of 2024-01-01)");
+            assert_eq!(table.evaluate_str("1 GBP").unwrap_err(), "MissingRate GBP");
+        }
+    }
+}
@@ -0,0 +1,333 @@
+#[allow(dead_code)]
+pub mod datetime{
+    use std::{
+        collections::HashMap,
+        fmt,
+        time::{
+            SystemTime,
+            UNIX_EPOCH
+        }
+    };
+    use crate::calculator_model::model::{
+        parse_number,
+        parse_to_tree_with_literals,
+        EvalError,
+        Function,
+        Literals,
+        Tree,
+        Value
+    };
+    use crate::calculator_number::number::Number;
+
+    const MINUTE: f64 = 60f64;
+    const HOUR: f64 = 3600f64;
+    const DAY: f64 = 86400f64;
+    // Duration names and their length in seconds. Months and years vary, so they are left out.
+    const DURATIONS: [(&str, f64); 20] = [
+        ("ms", 1e-3), ("s", 1f64), ("sec", 1f64), ("second", 1f64), ("seconds", 1f64),
+        ("min", MINUTE), ("minute", MINUTE), ("minutes", MINUTE),
+        ("h", HOUR), ("hr", HOUR), ("hour", HOUR), ("hours", HOUR),
+        ("d", DAY), ("day", DAY), ("days", DAY),
+        ("w", 7f64 * DAY), ("wk", 7f64 * DAY), ("week", 7f64 * DAY), ("weeks", 7f64 * DAY),
+        ("fortnight", 14f64 * DAY)
+    ];
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar.
+    fn days_from_civil(year: i64, month: i64, day: i64) -> i64{
+        let year = if month <= 2 {year - 1} else {year};
+        let era = year.div_euclid(400);
+        let yoe = year - era * 400;
+        let doy = (153 * (month + if month > 2 {-3} else {9}) + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+
+    fn civil_from_days(days: i64) -> (i64, i64, i64){
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 {mp + 3} else {mp - 9};
+        (if month <= 2 {yoe + era * 400 + 1} else {yoe + era * 400}, month, day)
+    }
+
+    // "2026-10-18", "2026-10-18T09:30" or "2026-10-18T09:30:15", all UTC.
+    fn parse_instant(src: &str) -> Result<f64, &'static str>{
+        let (date, time) = src.split_once('T').unwrap_or((src, "00:00"));
+        let field = |s: &str| s.parse::<i64>().map_err(|_| "Bad date");
+        let parts: Vec<&str> = date.split('-').collect();
+        let (year, month, day) = match parts[..]{
+            [y, m, d] => (field(y)?, field(m)?, field(d)?),
+            _ => return Err("Bad date")
+        };
+        let days = days_from_civil(year, month, day);
+        if !(1..=12).contains(&month) || civil_from_days(days) != (year, month, day){
+            return Err("Bad date");
+        }
+        let parts: Vec<&str> = time.split(':').collect();
+        let (hour, minute, second) = match parts[..]{
+            [h, m] => (field(h)?, field(m)?, 0),
+            [h, m, s] => (field(h)?, field(m)?, field(s)?),
+            _ => return Err("Bad time")
+        };
+        if hour > 23 || minute > 59 || second > 59{
+            return Err("Bad time");
+        }
+        Ok((days * 86400 + hour * 3600 + minute * 60 + second) as f64)
+    }
+
+    fn duration_unit(name: &str) -> Option<f64>{
+        DURATIONS.iter().find(|(n, _)| *n == name).map(|(_, secs)| *secs)
+    }
+
+    // "3h 20min", each part a number glued to a duration name.
+    fn parse_duration(src: &str) -> Result<f64, &'static str>{
+        let mut total = 0f64;
+        for part in src.split(' '){
+            let split = part.find(|c: char| c.is_ascii_alphabetic()).ok_or("Bad duration")?;
+            let (num, unit) = part.split_at(split);
+            total += parse_number(num)? * duration_unit(unit).ok_or("Unknown duration unit")?;
+        }
+        Ok(total)
+    }
+
+    // Plain numbers, lengths of time in seconds and points in time in seconds since 1970 UTC.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum TimeValue{
+        Number(f64),
+        Duration(f64),
+        Instant(f64)
+    }
+
+    impl TimeValue{
+        pub fn parse_literal(src: &str) -> Result<TimeValue, &'static str>{
+            if src.contains(|c: char| c.is_ascii_alphabetic()) && !src.contains('-'){
+                Ok(TimeValue::Duration(parse_duration(src)?))
+            }else if src.contains('-'){
+                Ok(TimeValue::Instant(parse_instant(src)?))
+            }else{
+                Ok(TimeValue::Number(parse_number(src)?))
+            }
+        }
+
+        pub fn now() -> TimeValue{
+            let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0f64, |d| d.as_secs_f64());
+            TimeValue::Instant(secs)
+        }
+
+        pub fn today() -> TimeValue{
+            match TimeValue::now(){
+                TimeValue::Instant(secs) => TimeValue::Instant((secs / DAY).floor() * DAY),
+                other => other
+            }
+        }
+
+        fn number(&self) -> Result<f64, EvalError>{
+            match self{
+                TimeValue::Number(x) => Ok(*x),
+                _ => Err(EvalError::TypeMismatch)
+            }
+        }
+    }
+
+    // 3d 4h 5min 6s with the zero parts left out.
+    fn write_duration(f: &mut fmt::Formatter, secs: f64) -> fmt::Result{
+        if secs < 0f64{
+            write!(f, "-")?;
+        }
+        let mut rest = secs.abs();
+        let mut parts = Vec::new();
+        for (name, size) in [("d", DAY), ("h", HOUR), ("min", MINUTE)]{
+            let whole = (rest / size).floor();
+            if whole > 0f64{
+                parts.push(format!("{}{}", whole, name));
+                rest -= whole * size;
+            }
+        }
+        if rest > 0f64 || parts.is_empty(){
+            parts.push(format!("{}s", rest));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+
+    impl fmt::Display for TimeValue{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            match self{
+                TimeValue::Number(x) => write!(f, "{}", x),
+                TimeValue::Duration(secs) => write_duration(f, *secs),
+                TimeValue::Instant(secs) => {
+                    let days = (secs / DAY).floor();
+                    let (year, month, day) = civil_from_days(days as i64);
+                    write!(f, "{:04}-{:02}-{:02}", year, month, day)?;
+                    let rest = secs - days * DAY;
+                    let (hour, minute, second) = ((rest / HOUR).floor(), (rest % HOUR / MINUTE).floor(), rest % MINUTE);
+                    match (rest == 0f64, second == 0f64){
+                        (true, _) => Ok(()),
+                        (false, true) => write!(f, "T{:02}:{:02}", hour, minute),
+                        (false, false) => write!(f, "T{:02}:{:02}:{:02}", hour, minute, second)
+                    }
+                }
+            }
+        }
+    }
+
+    impl Number for TimeValue{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            TimeValue::parse_literal(src)
+        }
+
+        fn zero() -> Self{
+            TimeValue::Number(0f64)
+        }
+
+        fn one() -> Self{
+            TimeValue::Number(1f64)
+        }
+
+        fn to_f64(&self) -> f64{
+            match self{
+                TimeValue::Number(x) => *x,
+                _ => f64::NAN
+            }
+        }
+
+        // A bare zero adds to anything, so sums of durations work.
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            use TimeValue::*;
+            match (*self, *other){
+                (Number(a), Number(b)) => Ok(Number(a + b)),
+                (Duration(a), Duration(b)) => Ok(Duration(a + b)),
+                (Instant(a), Duration(b)) | (Duration(b), Instant(a)) => Ok(Instant(a + b)),
+                (Number(z), other) | (other, Number(z)) if z == 0f64 => Ok(other),
+                _ => Err(EvalError::TypeMismatch)
+            }
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            use TimeValue::*;
+            match (*self, *other){
+                (Number(a), Number(b)) => Ok(Number(a - b)),
+                (Duration(a), Duration(b)) => Ok(Duration(a - b)),
+                (Instant(a), Duration(b)) => Ok(Instant(a - b)),
+                (Instant(a), Instant(b)) => Ok(Duration(a - b)),
+                (Number(0f64), Duration(b)) => Ok(Duration(-b)),
+                (other, Number(0f64)) => Ok(other),
+                _ => Err(EvalError::TypeMismatch)
+            }
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            use TimeValue::*;
+            match (*self, *other){
+                (Number(a), Number(b)) => Ok(Number(a * b)),
+                (Duration(a), Number(b)) | (Number(b), Duration(a)) => Ok(Duration(a * b)),
+                _ => Err(EvalError::TypeMismatch)
+            }
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            use TimeValue::*;
+            match (*self, *other){
+                (_, Number(0f64) | Duration(0f64)) => Err(EvalError::DivisionByZero),
+                (Number(a), Number(b)) => Ok(Number(a / b)),
+                (Duration(a), Number(b)) => Ok(Duration(a / b)),
+                (Duration(a), Duration(b)) => Ok(Number(a / b)),
+                _ => Err(EvalError::TypeMismatch)
+            }
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(TimeValue::Number(self.number()?.powf(other.number()?)))
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            match (self, func){
+                (TimeValue::Duration(secs), Function::Neg | Function::Abs | Function::Re | Function::Conj) => Ok(TimeValue::Duration(func.evaluate(*secs))),
+                (TimeValue::Instant(_), Function::Re | Function::Conj) => Ok(*self),
+                _ => Ok(TimeValue::Number(func.evaluate(self.number()?)))
+            }
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            Ok(TimeValue::Number(func.evaluate_binary(self.number()?, other.number()?)))
+        }
+    }
+
+    pub fn parse_time(src: &str) -> Result<Tree<Value<TimeValue>>, &'static str>{
+        parse_to_tree_with_literals(src, Literals{dates: true, ..Literals::default()}, TimeValue::parse_literal)
+    }
+
+    // Duration names, now and today.
+    pub fn time_env() -> HashMap<String, TimeValue>{
+        let mut env: HashMap<String, TimeValue> = DURATIONS.iter().map(|(name, secs)| (name.to_string(), TimeValue::Duration(*secs))).collect();
+        env.insert("now".to_string(), TimeValue::now());
+        env.insert("today".to_string(), TimeValue::today());
+        env
+    }
+
+    // "now - 2026-01-01 in hours" gives a plain count of the target duration.
+    pub fn evaluate_str(src: &str) -> Result<String, String>{
+        let split = src.rsplit_once(" in ").or_else(|| src.rsplit_once(" to "));
+        let (expr, target) = match split{
+            Some((expr, target)) => (expr, Some(target.trim())),
+            None => (src, None)
+        };
+        let env = time_env();
+        let value = parse_time(expr)?.evaluate_number(&env).map_err(|e| e.to_string())?;
+        match target{
+            Some(target) => {
+                let unit = parse_time(target)?.evaluate_number(&env).map_err(|e| e.to_string())?;
+                match (value, unit){
+                    (TimeValue::Duration(a), TimeValue::Duration(b)) => Ok(format!("{} {}", a / b, target)),
+                    _ => Err(EvalError::TypeMismatch.to_string())
+                }
+            },
+            None => Ok(value.to_string())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree_with;
+
+        #[test]
+        fn dates_are_lexed_only_in_date_mode(){
+            let tree = parse_to_tree_with("1000-10-10", f64::from_literal).unwrap();
+            assert_eq!(tree.evaluate_number(&HashMap::new()).unwrap(), 980f64);
+            assert!(parse_to_tree_with("3h 20min", f64::from_literal).is_err());
+            assert_eq!(evaluate_str("1000-10-10").unwrap(), "1000-10-10");
+            assert_eq!(evaluate_str("3h 20min in minutes").unwrap(), "200 minutes");
+        }
+
+        #[test]
+        fn date_arithmetic(){
+            assert_eq!(evaluate_str("2024-03-01 - 2024-02-01").unwrap(), "29d");
+            assert_eq!(evaluate_str("2024-01-31 + 1d").unwrap(), "2024-02-01");
+            assert_eq!(evaluate_str("2024-01-01T12:00 + 36h").unwrap(), "2024-01-03");
+            assert_eq!(evaluate_str("2024-01-01T09:30 - 2024-01-01").unwrap(), "9h 30min");
+            assert_eq!(evaluate_str("1w + 2d in days").unwrap(), "9 days");
+            assert_eq!(evaluate_str("2h * 3").unwrap(), "6h");
+        }
+
+        #[test]
+        fn bad_dates_and_mixed_types(){
+            assert_eq!(evaluate_str("2023-02-29").unwrap_err(), "Bad date");
+            assert_eq!(evaluate_str("2024-01-01T24:00").unwrap_err(), "Bad time");
+            assert_eq!(evaluate_str("2024-01-01 + 2024-01-01").unwrap_err(), "TypeMismatch");
+            assert_eq!(evaluate_str("2024-01-01 * 2").unwrap_err(), "TypeMismatch");
+        }
+
+        #[test]
+        fn durations_divide_into_ratios(){
+            assert_eq!(evaluate_str("1h / (30min)").unwrap(), "2");
+            assert_eq!(evaluate_str("2h / 2").unwrap(), "1h");
+            assert_eq!(evaluate_str("1h / 0").unwrap_err(), "DivisionByZero");
+            assert_eq!(evaluate_str("1h / 0h").unwrap_err(), "DivisionByZero");
+            assert_eq!(evaluate_str("2024-01-02 / 2").unwrap_err(), "TypeMismatch");
+        }
+    }
+}
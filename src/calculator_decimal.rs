@@ -0,0 +1,454 @@
+#[allow(dead_code)]
+pub mod decimal{
+    use std::{
+        cmp::Ordering,
+        collections::HashMap,
+        fmt
+    };
+    use crate::calculator_bigint::bigint::BigInt;
+    use crate::calculator_number::number::Number;
+    use crate::calculator_model::model::{
+        parse_to_tree_with,
+        EvalError,
+        Function,
+        Tree,
+        Value
+    };
+
+    // Integer powers whose digits would need more bits than this are rejected.
+    const MAX_BITS: u64 = 1 << 24;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Rounding{
+        HalfEven,
+        HalfUp,
+        HalfDown,
+        Up,
+        Down,
+        Ceiling,
+        Floor
+    }
+
+    impl Rounding{
+        pub fn from_name(name: &str) -> Option<Rounding>{
+            match name{
+                "half-even" => Some(Rounding::HalfEven),
+                "half-up" => Some(Rounding::HalfUp),
+                "half-down" => Some(Rounding::HalfDown),
+                "up" => Some(Rounding::Up),
+                "down" => Some(Rounding::Down),
+                "ceiling" => Some(Rounding::Ceiling),
+                "floor" => Some(Rounding::Floor),
+                _ => None
+            }
+        }
+    }
+
+    impl fmt::Display for Rounding{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            match self{
+                Rounding::HalfEven => write!(f, "half-even"),
+                Rounding::HalfUp => write!(f, "half-up"),
+                Rounding::HalfDown => write!(f, "half-down"),
+                Rounding::Up => write!(f, "up"),
+                Rounding::Down => write!(f, "down"),
+                Rounding::Ceiling => write!(f, "ceiling"),
+                Rounding::Floor => write!(f, "floor")
+            }
+        }
+    }
+
+    // Digits kept after the point by division and by the final result.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct DecimalContext{
+        pub scale: u32,
+        pub rounding: Rounding
+    }
+
+    impl Default for DecimalContext{
+        fn default() -> Self{
+            DecimalContext{scale: 28, rounding: Rounding::HalfEven}
+        }
+    }
+
+    // num / den rounded to an integer, den must not be zero.
+    fn divide_rounded(num: &BigInt, den: &BigInt, rounding: Rounding) -> BigInt{
+        let (q, r) = match num.div_rem(den){
+            Some(qr) => qr,
+            None => return BigInt::zero()
+        };
+        if r.is_zero(){
+            return q;
+        }
+        let negative = num.is_negative() != den.is_negative();
+        let half = r.abs().shifted_left(1).cmp(&den.abs());
+        let away = match rounding{
+            Rounding::Up => true,
+            Rounding::Down => false,
+            Rounding::Ceiling => !negative,
+            Rounding::Floor => negative,
+            Rounding::HalfUp => half != Ordering::Less,
+            Rounding::HalfDown => half == Ordering::Greater,
+            Rounding::HalfEven => half == Ordering::Greater || (half == Ordering::Equal && !q.is_even())
+        };
+        match (away, negative){
+            (false, _) => q,
+            (true, false) => &q + &BigInt::one(),
+            (true, true) => &q - &BigInt::one()
+        }
+    }
+
+    fn ten_pow(exp: u32) -> BigInt{
+        BigInt::from(10u64).pow(exp as u64)
+    }
+
+    // digits * 10^-scale, trailing zeros are kept so 1.50 stays 1.50.
+    #[derive(Clone, Debug)]
+    pub struct Decimal{
+        digits: BigInt,
+        scale: u32
+    }
+
+    impl Decimal{
+        pub fn zero() -> Decimal{
+            Decimal{digits: BigInt::zero(), scale: 0}
+        }
+
+        pub fn one() -> Decimal{
+            Decimal{digits: BigInt::one(), scale: 0}
+        }
+
+        pub fn from_integer(digits: BigInt) -> Decimal{
+            Decimal{digits, scale: 0}
+        }
+
+        // Optional sign, digits and at most one point, e.g. "-19.990".
+        pub fn parse(src: &str) -> Option<Decimal>{
+            let (whole, frac) = src.split_once('.').unwrap_or((src, ""));
+            if whole.trim_start_matches(['-', '+']).is_empty() && frac.is_empty(){
+                return None;
+            }
+            let digits = BigInt::parse(&format!("{}{}", whole, frac))?;
+            Some(Decimal{digits, scale: frac.len() as u32})
+        }
+
+        pub fn parse_literal(src: &str) -> Result<Decimal, &'static str>{
+            Decimal::parse(src).ok_or("Bad decimal")
+        }
+
+        pub fn digits(&self) -> &BigInt{
+            &self.digits
+        }
+
+        pub fn scale(&self) -> u32{
+            self.scale
+        }
+
+        pub fn is_zero(&self) -> bool{
+            self.digits.is_zero()
+        }
+
+        fn rescaled(&self, scale: u32) -> BigInt{
+            &self.digits * &ten_pow(scale - self.scale)
+        }
+
+        fn aligned(&self, other: &Decimal) -> (BigInt, BigInt, u32){
+            let scale = self.scale.max(other.scale);
+            (self.rescaled(scale), other.rescaled(scale), scale)
+        }
+
+        pub fn add(&self, other: &Decimal) -> Decimal{
+            let (a, b, scale) = self.aligned(other);
+            Decimal{digits: &a + &b, scale}
+        }
+
+        pub fn sub(&self, other: &Decimal) -> Decimal{
+            let (a, b, scale) = self.aligned(other);
+            Decimal{digits: &a - &b, scale}
+        }
+
+        pub fn mul(&self, other: &Decimal) -> Decimal{
+            Decimal{digits: &self.digits * &other.digits, scale: self.scale + other.scale}
+        }
+
+        pub fn neg(&self) -> Decimal{
+            Decimal{digits: -&self.digits, scale: self.scale}
+        }
+
+        pub fn abs(&self) -> Decimal{
+            Decimal{digits: self.digits.abs(), scale: self.scale}
+        }
+
+        // The quotient is rounded to the context scale, then loses trailing zeros down to the scale it would have if exact.
+        pub fn div(&self, other: &Decimal, ctx: &DecimalContext) -> Result<Decimal, EvalError>{
+            if other.is_zero(){
+                return Err(EvalError::DivisionByZero);
+            }
+            // self / other * 10^ctx.scale as a ratio of integers
+            let shift = ctx.scale as i64 + other.scale as i64 - self.scale as i64;
+            let (num, den) = if shift >= 0{
+                (&self.digits * &ten_pow(shift as u32), other.digits.clone())
+            }else{
+                (self.digits.clone(), &other.digits * &ten_pow(-shift as u32))
+            };
+            let quotient = Decimal{digits: divide_rounded(&num, &den, ctx.rounding), scale: ctx.scale};
+            Ok(quotient.trimmed(self.scale.saturating_sub(other.scale).min(ctx.scale)))
+        }
+
+        // Fewer digits after the point are left alone, more are rounded away.
+        pub fn round(&self, scale: u32, rounding: Rounding) -> Decimal{
+            if self.scale <= scale{
+                return self.clone();
+            }
+            let digits = divide_rounded(&self.digits, &ten_pow(self.scale - scale), rounding);
+            Decimal{digits, scale}
+        }
+
+        pub fn normalized(&self) -> Decimal{
+            self.trimmed(0)
+        }
+
+        // Drops trailing zeros after the point while more than min_scale digits are left.
+        pub fn trimmed(&self, min_scale: u32) -> Decimal{
+            let mut out = self.clone();
+            let ten = BigInt::from(10u64);
+            while out.scale > min_scale{
+                match out.digits.div_rem(&ten){
+                    Some((q, r)) if r.is_zero() => {
+                        out.digits = q;
+                        out.scale -= 1;
+                    },
+                    _ => break
+                }
+            }
+            out
+        }
+
+        // Only integer exponents, negative ones divide with the context.
+        pub fn pow(&self, exp: &Decimal, ctx: &DecimalContext) -> Result<Decimal, EvalError>{
+            let exp = exp.normalized();
+            if exp.scale > 0{
+                return Err(EvalError::NotExact);
+            }
+            let exp = match exp.digits.to_i64(){
+                Some(exp) => exp,
+                None => return Err(EvalError::Overflow)
+            };
+            let bits = self.digits.bits().max(self.scale as u64 * 4);
+            if bits > 1 && bits.saturating_mul(exp.unsigned_abs()) > MAX_BITS{
+                return Err(EvalError::Overflow);
+            }
+            let power = Decimal{
+                digits: self.digits.pow(exp.unsigned_abs()),
+                scale: self.scale * exp.unsigned_abs() as u32
+            };
+            if exp < 0 {Decimal::one().div(&power, ctx)} else {Ok(power)}
+        }
+    }
+
+    impl PartialEq for Decimal{
+        fn eq(&self, other: &Self) -> bool{
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl Eq for Decimal{}
+
+    // Compares values, 1.50 equals 1.5.
+    impl Ord for Decimal{
+        fn cmp(&self, other: &Self) -> Ordering{
+            let (a, b, _) = self.aligned(other);
+            a.cmp(&b)
+        }
+    }
+
+    impl PartialOrd for Decimal{
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering>{
+            Some(self.cmp(other))
+        }
+    }
+
+    impl fmt::Display for Decimal{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            let scale = self.scale as usize;
+            let digits = format!("{:0>width$}", self.digits.abs().to_string(), width = scale + 1);
+            let (whole, frac) = digits.split_at(digits.len() - scale);
+            let sign = if self.digits.is_negative() {"-"} else {""};
+            if frac.is_empty(){
+                write!(f, "{}{}", sign, whole)
+            }else{
+                write!(f, "{}{}.{}", sign, whole, frac)
+            }
+        }
+    }
+
+    // A decimal with the context its divisions and powers round to. Literals carry the context they were read with,
+    // the zeros and ones the evaluator makes up carry none and take the other operand's.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct ContextDecimal{
+        pub value: Decimal,
+        ctx: Option<DecimalContext>
+    }
+
+    impl ContextDecimal{
+        pub fn new(value: Decimal, ctx: DecimalContext) -> ContextDecimal{
+            ContextDecimal{value, ctx: Some(ctx)}
+        }
+
+        fn with(&self, other: &ContextDecimal, value: Decimal) -> ContextDecimal{
+            ContextDecimal{value, ctx: self.ctx.or(other.ctx)}
+        }
+
+        fn bare(value: Decimal) -> ContextDecimal{
+            ContextDecimal{value, ctx: None}
+        }
+
+        fn context(&self, other: &ContextDecimal) -> DecimalContext{
+            self.ctx.or(other.ctx).unwrap_or_default()
+        }
+    }
+
+    impl fmt::Display for ContextDecimal{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            write!(f, "{}", self.value)
+        }
+    }
+
+    impl Number for ContextDecimal{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            Ok(ContextDecimal::bare(Decimal::parse_literal(src)?))
+        }
+
+        fn zero() -> Self{
+            ContextDecimal::bare(Decimal::zero())
+        }
+
+        fn one() -> Self{
+            ContextDecimal::bare(Decimal::one())
+        }
+
+        fn to_f64(&self) -> f64{
+            self.value.to_string().parse().unwrap_or(f64::NAN)
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.with(other, self.value.add(&other.value)))
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.with(other, self.value.sub(&other.value)))
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.with(other, self.value.mul(&other.value)))
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.with(other, self.value.div(&other.value, &self.context(other))?))
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.with(other, self.value.pow(&other.value, &self.context(other))?))
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            let value = match func{
+                Function::Neg => self.value.neg(),
+                Function::Abs => self.value.abs(),
+                Function::Fact => Decimal::from_integer(BigInt::factorial(self.value.to_count()?)?),
+                _ => return Err(EvalError::NotExact)
+            };
+            Ok(ContextDecimal{value, ctx: self.ctx})
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            match func{
+                Function::Binom => Ok(self.with(other, Decimal::from_integer(BigInt::binomial(self.value.to_count()?, other.value.to_count()?)?))),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+    }
+
+    impl Decimal{
+        // Non-negative whole numbers only, for factorials and binomials.
+        fn to_count(&self) -> Result<u64, EvalError>{
+            let whole = self.normalized();
+            if whole.scale != 0{
+                return Err(EvalError::NotExact);
+            }
+            whole.digits.to_u64().ok_or(EvalError::NotExact)
+        }
+    }
+
+    // Literals keep every digit as written, "0.1" is exactly one tenth, and carry the context.
+    pub fn parse_decimal(src: &str, ctx: DecimalContext) -> Result<Tree<Value<ContextDecimal>>, &'static str>{
+        parse_to_tree_with(src, |literal| Ok(ContextDecimal::new(Decimal::parse_literal(literal)?, ctx)))
+    }
+
+    impl Tree<Value<ContextDecimal>>{
+        // + - * are exact, division and the result are rounded to the context scale.
+        pub fn evaluate_decimal(&self, ctx: &DecimalContext) -> Result<Decimal, EvalError>{
+            Ok(self.evaluate_number(&HashMap::new())?.value.round(ctx.scale, ctx.rounding))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+
+        fn evaluate(src: &str, ctx: &DecimalContext) -> Result<String, EvalError>{
+            parse_decimal(src, *ctx).unwrap().evaluate_decimal(ctx).map(|num| num.to_string())
+        }
+
+        #[test]
+        fn sums_are_exact_and_keep_trailing_zeros(){
+            let ctx = DecimalContext::default();
+            assert_eq!(evaluate("0.1 + 0.2", &ctx).unwrap(), "0.3");
+            assert_eq!(evaluate("1.50 + 2.25", &ctx).unwrap(), "3.75");
+            assert_eq!(evaluate("1.50 * 2", &ctx).unwrap(), "3.00");
+        }
+
+        #[test]
+        fn division_follows_the_context(){
+            let ctx = DecimalContext{scale: 2, rounding: Rounding::HalfEven};
+            assert_eq!(evaluate("1 / 3", &ctx).unwrap(), "0.33");
+            assert_eq!(evaluate("1 / 8", &ctx).unwrap(), "0.12");
+            let up = DecimalContext{scale: 2, rounding: Rounding::Up};
+            assert_eq!(evaluate("1 / 3", &up).unwrap(), "0.34");
+            assert_eq!(evaluate("1 / 4", &ctx).unwrap(), "0.25");
+            assert!(matches!(evaluate("1 / (0.5 - 0.50)", &ctx), Err(EvalError::DivisionByZero)));
+            assert!(matches!(evaluate("0 ^ -1", &ctx), Err(EvalError::DivisionByZero)));
+        }
+
+        #[test]
+        fn aggregates_divide_in_the_context(){
+            let ctx = DecimalContext{scale: 2, rounding: Rounding::HalfEven};
+            assert_eq!(evaluate("sum(i, i, 1, 3)", &ctx).unwrap(), "6");
+            assert_eq!(evaluate("sum(1 / i, i, 1, 3)", &ctx).unwrap(), "1.83");
+            assert_eq!(evaluate("product(i / 2, i, 1, 4)", &ctx).unwrap(), "1.50");
+            assert!(matches!(evaluate("integrate(x, x, 0, 1)", &ctx), Err(EvalError::NotExact)));
+        }
+
+        #[test]
+        fn literals_keep_their_scale(){
+            let price = Decimal::parse("-19.990").unwrap();
+            assert_eq!((price.scale(), price.to_string()), (3, "-19.990".to_string()));
+            assert_eq!(Decimal::parse(".5").unwrap().to_string(), "0.5");
+            for src in ["", "-", ".", "1.2.3", "1e3", "0x10"]{
+                assert!(Decimal::parse(src).is_none(), "{}", src);
+            }
+            assert!(matches!(Decimal::parse_literal("1,5"), Err("Bad decimal")));
+        }
+
+        #[test]
+        fn rounding_modes(){
+            let half = Decimal::parse("-2.5").unwrap();
+            assert_eq!(half.round(0, Rounding::HalfEven).to_string(), "-2");
+            assert_eq!(half.round(0, Rounding::HalfUp).to_string(), "-3");
+            assert_eq!(half.round(0, Rounding::HalfDown).to_string(), "-2");
+            assert_eq!(half.round(0, Rounding::Ceiling).to_string(), "-2");
+            assert_eq!(half.round(0, Rounding::Floor).to_string(), "-3");
+            assert_eq!(Rounding::from_name("half-even"), Some(Rounding::HalfEven));
+        }
+    }
+}
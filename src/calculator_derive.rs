@@ -0,0 +1,174 @@
+#[allow(dead_code)]
+pub mod derive{
+    use crate::calculator_model::model::{
+        Aggregate,
+        EvalError,
+        Function,
+        Operator,
+        Tree,
+        Value
+    };
+    use crate::calculator_simplify::simplify::{
+        simplify_expr,
+        Expr
+    };
+
+    fn mul(left: Expr, right: Expr) -> Expr{
+        Expr::op(Operator::Mult, left, right)
+    }
+
+    fn div(left: Expr, right: Expr) -> Expr{
+        Expr::op(Operator::Div, left, right)
+    }
+
+    // Replaces free occurrences of var, an aggregate binding the same name shadows it.
+    fn substitute(expr: &Expr, var: &str, by: &Expr) -> Expr{
+        match expr{
+            Expr::Var(name) if name == var => by.clone(),
+            Expr::Num(_) | Expr::Var(_) => expr.clone(),
+            Expr::Op(op, u, v) => Expr::op(*op, substitute(u, var, by), substitute(v, var, by)),
+            Expr::Func(func, u) => Expr::func(*func, substitute(u, var, by)),
+            Expr::Func2(func, u, v) => Expr::func2(*func, substitute(u, var, by), substitute(v, var, by)),
+            Expr::Agg(agg, bound, body, from, to) => {
+                let body = if bound == var {*body.clone()} else {substitute(body, var, by)};
+                Expr::agg(*agg, bound, body, substitute(from, var, by), substitute(to, var, by))
+            }
+        }
+    }
+
+    pub fn derive_expr(expr: &Expr, var: &str) -> Expr{
+        match expr{
+            Expr::Num(_) => Expr::Num(0f64),
+            Expr::Var(name) => Expr::Num(if name == var {1f64} else {0f64}),
+            Expr::Op(op, u, v) => {
+                let du = derive_expr(u, var);
+                let dv = derive_expr(v, var);
+                let (u, v) = (*u.clone(), *v.clone());
+                match op{
+                    Operator::Plus | Operator::Minus => Expr::op(*op, du, dv),
+                    Operator::Mult => Expr::op(Operator::Plus, mul(du, v), mul(u, dv)),
+                    Operator::Div => {
+                        let numerator = Expr::op(Operator::Minus, mul(du, v.clone()), mul(u, dv));
+                        div(numerator, Expr::op(Operator::Pow, v, Expr::Num(2f64)))
+                    },
+                    Operator::Pow if !v.contains_var(var) => {
+                        let lowered = Expr::op(Operator::Pow, u, Expr::op(Operator::Minus, v.clone(), Expr::Num(1f64)));
+                        mul(mul(v, lowered), du)
+                    },
+                    Operator::Pow if !u.contains_var(var) => {
+                        mul(mul(expr.clone(), Expr::func(Function::Ln, u)), dv)
+                    },
+                    // d(u^v) = u^v * (v' ln u + v u' / u)
+                    Operator::Pow => {
+                        let inner = Expr::op(Operator::Plus, mul(dv, Expr::func(Function::Ln, u.clone())), div(mul(v, du), u));
+                        mul(expr.clone(), inner)
+                    },
+                    // Bitwise results step between integers, flat or undefined like factorials.
+                    _ => Expr::Num(if expr.contains_var(var) {f64::NAN} else {0f64})
+                }
+            },
+            // Factorials, binomials and bit functions only exist at integers, they have no derivative there.
+            Expr::Func(Function::Fact | Function::Not | Function::Popcount, _) | Expr::Func2(..) => {
+                Expr::Num(if expr.contains_var(var) {f64::NAN} else {0f64})
+            },
+            Expr::Func(func, u) => {
+                let du = derive_expr(u, var);
+                let u = *u.clone();
+                let outer = match func{
+                    Function::Sin => Expr::func(Function::Cos, u),
+                    Function::Cos => Expr::func(Function::Neg, Expr::func(Function::Sin, u)),
+                    Function::Tan => div(Expr::Num(1f64), Expr::op(Operator::Pow, Expr::func(Function::Cos, u), Expr::Num(2f64))),
+                    Function::Exp => expr.clone(),
+                    Function::Ln => div(Expr::Num(1f64), u),
+                    Function::Sqrt => div(Expr::Num(1f64), mul(Expr::Num(2f64), expr.clone())),
+                    Function::Abs => div(u, expr.clone()),
+                    Function::Neg => return Expr::func(Function::Neg, du),
+                    // Linear maps, so the derivative passes through them.
+                    Function::Re | Function::Im | Function::Conj => return Expr::func(*func, du),
+                    Function::Arg => Expr::Num(0f64),
+                    _ => Expr::Num(f64::NAN)
+                };
+                mul(outer, du)
+            },
+            Expr::Agg(agg, bound, body, from, to) => {
+                let inner = if bound == var {Expr::Num(0f64)} else {derive_expr(body, var)};
+                let da = derive_expr(from, var);
+                let db = derive_expr(to, var);
+                let (from, to) = (*from.clone(), *to.clone());
+                match agg{
+                    // Leibniz rule, the bounds move the ends of the integral
+                    Aggregate::Integrate => {
+                        let upper = mul(substitute(body, bound, &to), db);
+                        let lower = mul(substitute(body, bound, &from), da);
+                        let inside = Expr::agg(*agg, bound, inner, from, to);
+                        Expr::op(Operator::Plus, inside, Expr::op(Operator::Minus, upper, lower))
+                    },
+                    // The term count only changes in jumps, moving `from` shifts every term by da.
+                    Aggregate::Sum => {
+                        let term = Expr::op(Operator::Plus, inner, mul(derive_expr(body, bound), da));
+                        Expr::agg(*agg, bound, term, from, to)
+                    },
+                    Aggregate::Product => {
+                        let term = Expr::op(Operator::Plus, inner, mul(derive_expr(body, bound), da));
+                        let logarithmic = Expr::agg(Aggregate::Sum, bound, div(term, *body.clone()), from, to);
+                        mul(expr.clone(), logarithmic)
+                    }
+                }
+            }
+        }
+    }
+
+    // Symbolic zeros from the product and chain rules are dropped, treating variables as finite.
+    pub fn derivative(tree: &Tree<Value>, var: &str) -> Result<Tree<Value>, EvalError>{
+        let expr = Expr::from_tree(tree)?;
+        Ok(simplify_expr(derive_expr(&expr, var), true).to_tree())
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::{
+            parse_to_tree,
+            Env
+        };
+
+        // The derivative by var of src, evaluated with the given variables.
+        fn slope(src: &str, var: &str, at: &[(&str, f64)]) -> f64{
+            let env: Env = at.iter().map(|(name, value)| (name.to_string(), *value)).collect();
+            derivative(&parse_to_tree(src).unwrap(), var).unwrap().rec_evaluate_env(&env).unwrap()
+        }
+
+        fn close(a: f64, b: f64) -> bool{
+            (a - b).abs() < 1e-12 * (1f64 + b.abs())
+        }
+
+        #[test]
+        fn product_and_quotient_rules(){
+            assert!(close(slope("x^2 * sin(x)", "x", &[("x", 1f64)]), 2f64 * 1f64.sin() + 1f64.cos()));
+            assert!(close(slope("x / (1 + x)", "x", &[("x", 2f64)]), 1f64 / 9f64));
+            assert!(close(slope("3 * x^4", "x", &[("x", 2f64)]), 96f64));
+        }
+
+        #[test]
+        fn chain_rule_through_ln_and_sqrt(){
+            assert!(close(slope("ln(x^2 + 1)", "x", &[("x", 2f64)]), 0.8));
+            assert!(close(slope("sqrt(1 + 3 * x)", "x", &[("x", 5f64)]), 0.375));
+            assert!(close(slope("exp(sin(x))", "x", &[("x", 0f64)]), 1f64));
+            assert!(close(slope("2 ^ x", "x", &[("x", 3f64)]), 8f64 * 2f64.ln()));
+            assert!(close(slope("x ^ x", "x", &[("x", 2f64)]), 4f64 * (2f64.ln() + 1f64)));
+        }
+
+        #[test]
+        fn other_variables_are_constants(){
+            assert!(close(slope("x * y + y^2", "y", &[("x", 3f64), ("y", 2f64)]), 7f64));
+            assert!(close(slope("x * y + y^2", "x", &[("x", 3f64), ("y", 2f64)]), 2f64));
+            assert_eq!(derivative(&parse_to_tree("sin(y)").unwrap(), "x").unwrap().to_string(), "0");
+        }
+
+        #[test]
+        fn integrals_move_with_their_bounds(){
+            assert!(close(slope("integrate(t^2, t, 0, x)", "x", &[("x", 3f64)]), 9f64));
+            assert!(slope("x!", "x", &[("x", 3f64)]).is_nan());
+        }
+    }
+}
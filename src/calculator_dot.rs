@@ -0,0 +1,85 @@
+#[allow(dead_code)]
+pub mod dot{
+    use std::fmt::{
+        self,
+        Write
+    };
+    use crate::calculator_model::model::{
+        Node,
+        Tree
+    };
+
+    fn escape(label: &str) -> String{
+        let mut out = String::new();
+        for c in label.chars(){
+            match c{
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                _ => out.push(c)
+            }
+        }
+        out
+    }
+
+    // Writes the node and its subtree, returns the next free node id.
+    fn write_node<T: fmt::Display>(out: &mut String, node: &Node<T>, id: usize) -> Result<usize, fmt::Error>{
+        let label = match &node.value{
+            Some(val) => val.to_string(),
+            None => "None".to_string()
+        };
+        writeln!(out, "    n{} [label=\"{}\"];", id, escape(&label))?;
+        let mut next = id + 1;
+        for (child, edge) in [(node.left_node(), "left"), (node.right_node(), "right")]{
+            if let Some(n) = child{
+                let child_id = next;
+                next = write_node(out, n, child_id)?;
+                writeln!(out, "    n{} -> n{} [label=\"{}\"];", id, child_id, edge)?;
+            }
+        }
+        Ok(next)
+    }
+
+    impl<T: fmt::Display> Tree<T>{
+        pub fn to_dot(&self) -> String{
+            let mut out = String::from("digraph tree {\n    node [shape=circle];\n");
+            if let Some(root) = self.root_node(){
+                let _ = write_node(&mut out, root, 0);
+            }
+            out.push_str("}\n");
+            out
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::{parse_to_tree, Value};
+
+        #[test]
+        fn nodes_are_numbered_depth_first(){
+            let dot = parse_to_tree("1 + -x").unwrap().to_dot();
+            assert_eq!(dot.lines().collect::<Vec<_>>(), [
+                "digraph tree {",
+                "    node [shape=circle];",
+                "    n0 [label=\"+\"];",
+                "    n1 [label=\"1\"];",
+                "    n0 -> n1 [label=\"left\"];",
+                "    n2 [label=\"neg\"];",
+                "    n3 [label=\"x\"];",
+                "    n2 -> n3 [label=\"left\"];",
+                "    n0 -> n2 [label=\"right\"];",
+                "}"
+            ]);
+            assert_eq!(Tree::<Value>::new().to_dot(), "digraph tree {\n    node [shape=circle];\n}\n");
+        }
+
+        #[test]
+        fn labels_are_escaped(){
+            assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+            let mut tree: Tree<String> = Tree::new();
+            tree.set_current("say \"hi\"".to_string());
+            assert!(tree.to_dot().contains("n0 [label=\"say \\\"hi\\\"\"];"));
+        }
+    }
+}
@@ -0,0 +1,260 @@
+#[allow(dead_code)]
+pub mod format{
+    // Significant figures are written out in full between 1e-7 and 1e21, in scientific notation outside.
+    const POSITIONAL_EXPONENTS: std::ops::Range<i32> = -7..21;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Notation{
+        // Shortest text that reads back as the same f64.
+        Auto,
+        Fixed,
+        Scientific,
+        // Scientific with the exponent a multiple of three.
+        Engineering,
+        Significant
+    }
+
+    impl Notation{
+        pub fn from_name(name: &str) -> Option<Notation>{
+            match name{
+                "auto" => Some(Notation::Auto),
+                "fixed" => Some(Notation::Fixed),
+                "sci" | "scientific" => Some(Notation::Scientific),
+                "eng" | "engineering" => Some(Notation::Engineering),
+                "sig" | "significant" => Some(Notation::Significant),
+                _ => None
+            }
+        }
+    }
+
+    // How a result is printed. Precision counts digits after the point, or significant digits for Significant.
+    #[derive(Clone, Copy, Debug)]
+    pub struct Format{
+        pub notation: Notation,
+        pub precision: usize,
+        pub separator: Option<char>,
+        pub trim: bool,
+        pub radix: u32
+    }
+
+    impl Default for Format{
+        fn default() -> Self{
+            Format{notation: Notation::Auto, precision: 6, separator: None, trim: false, radix: 10}
+        }
+    }
+
+    impl Format{
+        pub fn apply(&self, value: f64) -> String{
+            if self.radix != 10{
+                return to_radix(value, self.radix);
+            }
+            if !value.is_finite(){
+                return value.to_string();
+            }
+            let text = match self.notation{
+                Notation::Auto => value.to_string(),
+                Notation::Fixed => format!("{:.*}", self.precision, value),
+                Notation::Scientific => format!("{:.*e}", self.precision, value),
+                Notation::Engineering => engineering(value, self.precision),
+                Notation::Significant => significant(value, self.precision.max(1))
+            };
+            let text = if self.trim {trim_zeros(&text)} else {text};
+            match self.separator{
+                Some(separator) => group(&text, separator),
+                None => text
+            }
+        }
+    }
+
+    // Exponent of a number printed with {:e}.
+    fn split_exponent(text: &str) -> (&str, i32){
+        match text.split_once('e'){
+            Some((mantissa, exp)) => (mantissa, exp.parse().unwrap_or(0)),
+            None => (text, 0)
+        }
+    }
+
+    // Rounding 9.99 up can move the exponent into the next group of three, so it goes round again.
+    fn engineering(value: f64, precision: usize) -> String{
+        let mut exp = split_exponent(&format!("{:e}", value)).1;
+        loop{
+            let shift = exp.rem_euclid(3) as usize;
+            let text = format!("{:.*e}", precision + shift, value);
+            let (mantissa, rounded) = split_exponent(&text);
+            if rounded != exp{
+                exp = rounded;
+                continue;
+            }
+            let (sign, digits) = mantissa.split_at(if value < 0f64 {1} else {0});
+            let digits = digits.replace('.', "");
+            let (int, frac) = digits.split_at(shift + 1);
+            let point = if frac.is_empty() {""} else {"."};
+            return format!("{}{}{}{}e{}", sign, int, point, frac, exp - shift as i32);
+        }
+    }
+
+    // The digits come from {:e} so they are rounded once, then placed around the point as text.
+    fn significant(value: f64, figures: usize) -> String{
+        let text = format!("{:.*e}", figures - 1, value);
+        let (mantissa, exp) = split_exponent(&text);
+        if !POSITIONAL_EXPONENTS.contains(&exp){
+            return text;
+        }
+        let (sign, digits) = mantissa.split_at(if value < 0f64 {1} else {0});
+        let digits = digits.replace('.', "");
+        let body = if exp < 0{
+            format!("0.{}{}", "0".repeat(exp.unsigned_abs() as usize - 1), digits)
+        }else if exp as usize + 1 >= digits.len(){
+            format!("{}{}", digits, "0".repeat(exp as usize + 1 - digits.len()))
+        }else{
+            format!("{}.{}", &digits[..exp as usize + 1], &digits[exp as usize + 1..])
+        };
+        format!("{}{}", sign, body)
+    }
+
+    // Drops zeros at the end of the fraction, and the point if nothing is left after it.
+    fn trim_zeros(text: &str) -> String{
+        let (mantissa, exp) = text.split_at(text.find('e').unwrap_or(text.len()));
+        if !mantissa.contains('.'){
+            return text.to_string();
+        }
+        format!("{}{}", mantissa.trim_end_matches('0').trim_end_matches('.'), exp)
+    }
+
+    // A point, sign, exponent or digit would read as part of the number.
+    pub fn is_separator(c: char) -> bool{
+        !c.is_ascii_digit() && !matches!(c, '.' | '-' | '+' | 'e' | 'E')
+    }
+
+    // Separator between every three digits before the point.
+    fn group(text: &str, separator: char) -> String{
+        let start = text.find(|c: char| c.is_ascii_digit()).unwrap_or(0);
+        let end = text[start..].find(|c: char| !c.is_ascii_digit()).map_or(text.len(), |i| start + i);
+        let digits = &text[start..end];
+        let mut out = text[..start].to_string();
+        for (i, d) in digits.chars().enumerate(){
+            if i > 0 && (digits.len() - i).is_multiple_of(3){
+                out.push(separator);
+            }
+            out.push(d);
+        }
+        out.push_str(&text[end..]);
+        out
+    }
+
+    // Bases a result can be shown in, by number or by name.
+    pub fn radix_from_name(name: &str) -> Option<u32>{
+        match name{
+            "2" | "bin" | "binary" => Some(2),
+            "8" | "oct" | "octal" => Some(8),
+            "10" | "dec" | "decimal" => Some(10),
+            "16" | "hex" | "hexadecimal" => Some(16),
+            _ => None
+        }
+    }
+
+    // The same prefixes the parser reads.
+    pub fn radix_prefix(radix: u32) -> &'static str{
+        match radix{
+            2 => "0b",
+            8 => "0o",
+            16 => "0x",
+            _ => ""
+        }
+    }
+
+    fn digit(d: u32, radix: u32) -> char{
+        std::char::from_digit(d, radix).map_or('?', |c| c.to_ascii_uppercase())
+    }
+
+    // Multiplying or dividing by a power of two never rounds, so every digit is exact and the fraction ends.
+    pub fn to_radix(value: f64, radix: u32) -> String{
+        if radix == 10 || !value.is_finite() || !matches!(radix, 2 | 8 | 16){
+            return value.to_string();
+        }
+        let base = radix as f64;
+        let mut int = value.abs().trunc();
+        let mut frac = value.abs() - int;
+        let mut digits = Vec::new();
+        while int >= 1f64{
+            digits.push(digit((int % base) as u32, radix));
+            int = (int / base).trunc();
+        }
+        if digits.is_empty(){
+            digits.push('0');
+        }
+        digits.reverse();
+        let mut out = format!("{}{}{}", if value < 0f64 {"-"} else {""}, radix_prefix(radix), digits.into_iter().collect::<String>());
+        if frac > 0f64{
+            out.push('.');
+            while frac > 0f64{
+                frac *= base;
+                out.push(digit(frac.trunc() as u32, radix));
+                frac -= frac.trunc();
+            }
+        }
+        out
+    }
+
+    // Raw bits, as a two's complement register would show them.
+    pub fn bits_to_radix(bits: u128, radix: u32) -> String{
+        match radix{
+            2 => format!("0b{:b}", bits),
+            8 => format!("0o{:o}", bits),
+            16 => format!("0x{:X}", bits),
+            _ => bits.to_string()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree;
+
+        #[test]
+        fn radix_output_reads_back(){
+            for (value, radix, text) in [(255.5, 16, "0xFF.8"), (10f64, 2, "0b1010"), (-8f64, 8, "-0o10"), (0.625, 2, "0b0.101")]{
+                assert_eq!(to_radix(value, radix), text);
+                assert_eq!(parse_to_tree(text).unwrap().rec_evaluate().unwrap(), value);
+            }
+            assert_eq!(to_radix(1.5, 10), "1.5");
+            assert_eq!(bits_to_radix(255, 16), "0xFF");
+            assert_eq!(radix_from_name("hex"), Some(16));
+            assert_eq!(radix_from_name("3"), None);
+        }
+
+        fn with(notation: Notation, precision: usize) -> Format{
+            Format{notation, precision, ..Format::default()}
+        }
+
+        #[test]
+        fn notations(){
+            assert_eq!(with(Notation::Fixed, 2).apply(1.23456), "1.23");
+            assert_eq!(with(Notation::Scientific, 3).apply(12345.678), "1.235e4");
+            assert_eq!(with(Notation::Engineering, 2).apply(12345.678), "12.35e3");
+            assert_eq!(with(Notation::Engineering, 1).apply(999.96), "1.0e3");
+            assert_eq!(with(Notation::Engineering, 1).apply(-0.00123), "-1.2e-3");
+            assert_eq!(with(Notation::Significant, 3).apply(0.000123456), "0.000123");
+            assert_eq!(with(Notation::Significant, 3).apply(123456f64), "123000");
+            assert_eq!(with(Notation::Significant, 3).apply(1.23456e25), "1.23e25");
+            assert_eq!(with(Notation::Significant, 4).apply(2.5), "2.500");
+            assert_eq!(with(Notation::Fixed, 2).apply(f64::INFINITY), "inf");
+            assert_eq!(Notation::from_name("eng"), Some(Notation::Engineering));
+            assert_eq!(Notation::from_name("roman"), None);
+        }
+
+        #[test]
+        fn trimming_and_grouping(){
+            let trimmed = |notation, precision| Format{trim: true, ..with(notation, precision)};
+            assert_eq!(trimmed(Notation::Fixed, 4).apply(2.5), "2.5");
+            assert_eq!(trimmed(Notation::Fixed, 4).apply(2f64), "2");
+            assert_eq!(trimmed(Notation::Scientific, 3).apply(1000f64), "1e3");
+            let grouped = |separator| Format{separator: Some(separator), ..Format::default()};
+            assert_eq!(grouped(',').apply(1234567.891), "1,234,567.891");
+            assert_eq!(grouped('_').apply(-1234f64), "-1_234");
+            assert_eq!(grouped(',').apply(123f64), "123");
+            assert!(is_separator(',') && is_separator(' ') && is_separator('_'));
+            assert!(!is_separator('.') && !is_separator('e') && !is_separator('7'));
+        }
+    }
+}
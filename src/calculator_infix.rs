@@ -0,0 +1,141 @@
+#[allow(dead_code)]
+pub mod infix{
+    use std::fmt;
+    use crate::calculator_model::model::{
+        Function,
+        Node,
+        Operator,
+        Tree,
+        Value
+    };
+
+    // How tightly the root of a subtree binds, unary minus sits between * and ^ like in the parser.
+    pub fn binding(node: &Node<Value>) -> u8{
+        match &node.value{
+            Some(Value::Operator(op)) => op.precedence(),
+            Some(Value::Function(Function::Neg)) => 7,
+            Some(Value::Value(num)) if num.is_sign_negative() => 7,
+            _ => 9
+        }
+    }
+
+    pub fn needs_parens(parent: &Operator, child: &Node<Value>, is_right: bool) -> bool{
+        let child_binding = binding(child);
+        match &child.value{
+            Some(Value::Function(Function::Neg)) if is_right => false,
+            Some(Value::Operator(_)) if child_binding == parent.precedence() => {
+                is_right != parent.right_assoc()
+            },
+            _ => child_binding < parent.precedence()
+        }
+    }
+
+    fn write_operand(f: &mut fmt::Formatter, node: Option<&Node<Value>>, parens: bool) -> fmt::Result{
+        match node{
+            Some(n) if parens => {
+                write!(f,"(")?;
+                write_infix(f, n)?;
+                write!(f,")")
+            },
+            Some(n) => write_infix(f, n),
+            None => write!(f,"0")
+        }
+    }
+
+    fn write_infix(f: &mut fmt::Formatter, node: &Node<Value>) -> fmt::Result{
+        match &node.value{
+            Some(Value::Operator(op)) => {
+                let left = node.left_node();
+                let right = node.right_node();
+                write_operand(f, left, left.is_some_and(|n| needs_parens(op, n, false)))?;
+                match op{
+                    Operator::Pow => write!(f,"{}",op)?,
+                    _ => write!(f," {} ",op)?
+                }
+                write_operand(f, right, right.is_some_and(|n| needs_parens(op, n, true)))
+            },
+            Some(Value::Function(Function::Neg)) => {
+                write!(f,"-")?;
+                let arg = node.left_node();
+                write_operand(f, arg, arg.is_some_and(|n| binding(n) < 7))
+            },
+            Some(Value::Function(Function::Fact)) => {
+                let arg = node.left_node();
+                write_operand(f, arg, arg.is_some_and(|n| binding(n) < 9))?;
+                write!(f,"!")
+            },
+            Some(Value::Function(func)) => {
+                write!(f,"{}(",func)?;
+                write_operand(f, node.left_node(), false)?;
+                if func.arity() == 2{
+                    write!(f,", ")?;
+                    write_operand(f, node.right_node(), false)?;
+                }
+                write!(f,")")
+            },
+            Some(Value::Aggregate(agg)) => {
+                write!(f,"{}(",agg)?;
+                write_operand(f, node.left_node(), false)?;
+                if let Some(range) = node.right_node(){
+                    write!(f,", ")?;
+                    write_infix(f, range)?;
+                }
+                write!(f,")")
+            },
+            Some(Value::Range(name)) => {
+                write!(f,"{}, ",name)?;
+                write_operand(f, node.left_node(), false)?;
+                write!(f,", ")?;
+                write_operand(f, node.right_node(), false)
+            },
+            Some(val) => write!(f,"{}",val),
+            None => write!(f,"None")
+        }
+    }
+
+    impl fmt::Display for Tree<Value>{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            match self.root_node(){
+                Some(root) => write_infix(f, root),
+                None => Ok(())
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use crate::calculator_model::model::{parse_to_tree, Env};
+
+        fn infix(src: &str) -> String{
+            parse_to_tree(src).unwrap().to_string()
+        }
+
+        #[test]
+        fn drops_redundant_parentheses(){
+            assert_eq!(infix("(1 + 2) + 3"), "1 + 2 + 3");
+            assert_eq!(infix("(2 * 3) / 4"), "2 * 3 / 4");
+            assert_eq!(infix("-(x^2)"), "-x^2");
+            assert_eq!(infix("((x))"), "x");
+            assert_eq!(infix("sin((x + 1))^2"), "sin(x + 1)^2");
+        }
+
+        #[test]
+        fn keeps_the_ones_that_matter(){
+            for src in ["1 - (2 - 3)", "1 + (2 + 3)", "2 * (3 / 4)", "(2^3)^2", "2^3^2", "(-2)^2", "-2^2",
+                "-(2 * x)", "(x - 1)!", "x^2!", "1 - -2", "2 * -x", "2^-1", "(1 + 2) * (3 - 4)"]{
+                assert_eq!(infix(src), src);
+            }
+        }
+
+        #[test]
+        fn printing_keeps_the_value(){
+            let env = Env::from([("x".to_string(), 3f64)]);
+            for src in ["1 - (2 - 3) * x", "-(-x)", "(x - 1)! / 2^-x", "2 ^ (1 / x) - -x", "binom(x + 2, 2)"]{
+                let mut tree = parse_to_tree(src).unwrap();
+                let mut printed = parse_to_tree(&tree.to_string()).unwrap();
+                assert_eq!(printed.to_string(), tree.to_string());
+                assert_eq!(printed.rec_evaluate_env(&env).unwrap(), tree.rec_evaluate_env(&env).unwrap(), "{}", src);
+            }
+        }
+    }
+}
@@ -0,0 +1,498 @@
+#[allow(dead_code)]
+pub mod interval{
+    use std::{
+        cmp::Ordering,
+        f64::consts::PI,
+        fmt
+    };
+    use crate::calculator_bigint::bigint::BigInt;
+    use crate::calculator_model::model::{
+        parse_number,
+        EvalError,
+        Function
+    };
+    use crate::calculator_number::number::Number;
+
+    // Below this the rounding error of a product is no longer exact, so results are widened blindly.
+    const TINY: f64 = 1e-290;
+    // Integers up to 2^53 convert to f64 without rounding.
+    const EXACT_INTEGER: f64 = 9007199254740992f64;
+    // Critical points of sin, cos and tan this close to an end are counted as inside.
+    const SLOP: f64 = 1e-9;
+
+    // Moves x one step in the rounding direction if the true value lies beyond it.
+    fn nudge(x: f64, err: f64, up: bool) -> f64{
+        if up && err > 0f64{
+            x.next_up()
+        }else if !up && err < 0f64{
+            x.next_down()
+        }else{
+            x
+        }
+    }
+
+    // A finite result that overflowed is only an upper bound in its own direction.
+    fn overflowed(x: f64, up: bool) -> f64{
+        if up == (x > 0f64) {x} else {f64::MAX.copysign(x)}
+    }
+
+    // a + b rounded down or up, the error term is exact (TwoSum).
+    fn sum(a: f64, b: f64, up: bool) -> f64{
+        let s = a + b;
+        if s.is_infinite() && a.is_finite() && b.is_finite(){
+            return overflowed(s, up);
+        }
+        if !s.is_finite(){
+            return s;
+        }
+        let bb = s - a;
+        nudge(s, (a - (s - bb)) + (b - bb), up)
+    }
+
+    // Zero times anything, infinity included, is zero.
+    fn product(a: f64, b: f64, up: bool) -> f64{
+        if a == 0f64 || b == 0f64{
+            return 0f64;
+        }
+        let p = a * b;
+        if p.is_infinite() && a.is_finite() && b.is_finite(){
+            return overflowed(p, up);
+        }
+        if !p.is_finite(){
+            return p;
+        }
+        if p.abs() < TINY{
+            return if up {p.next_up()} else {p.next_down()};
+        }
+        nudge(p, a.mul_add(b, -p), up)
+    }
+
+    fn quotient(a: f64, b: f64, up: bool) -> f64{
+        let q = a / b;
+        if q.is_infinite() && a.is_finite(){
+            return overflowed(q, up);
+        }
+        if !q.is_finite() || b.is_infinite(){
+            return q;
+        }
+        if q.abs() < TINY && a != 0f64{
+            return if up {q.next_up()} else {q.next_down()};
+        }
+        let rem = (-q).mul_add(b, a);
+        nudge(q, if b > 0f64 {rem} else {-rem}, up)
+    }
+
+    fn square_root(a: f64, up: bool) -> f64{
+        let r = a.sqrt();
+        if !r.is_finite() || r == 0f64{
+            return r;
+        }
+        nudge(r, -r.mul_add(r, -a), up)
+    }
+
+    // a^n by squaring, every step rounded the same way.
+    fn power(a: f64, n: u64, up: bool) -> f64{
+        if a < 0f64{
+            return if n.is_multiple_of(2) {power(-a, n, up)} else {-power(-a, n, !up)};
+        }
+        let (mut base, mut n, mut out) = (a, n, 1f64);
+        while n > 0{
+            if n & 1 == 1{
+                out = product(out, base, up);
+            }
+            n >>= 1;
+            if n > 0{
+                base = product(base, base, up);
+            }
+        }
+        out
+    }
+
+    fn factorial(n: f64, up: bool) -> f64{
+        let mut out = 1f64;
+        let mut i = 2f64;
+        while i <= n && out.is_finite(){
+            out = product(out, i, up);
+            i += 1f64;
+        }
+        out
+    }
+
+    // Whether point + 2k*period lies in [lo, hi] for some integer k, counting near misses.
+    fn hits(lo: f64, hi: f64, point: f64, period: f64) -> bool{
+        let slop = SLOP * lo.abs().max(hi.abs()).max(1f64);
+        let k = ((lo - slop - point) / period).ceil();
+        point + k * period <= hi + slop
+    }
+
+    // Splits a decimal literal into sign, digits and count of digits after the point.
+    fn decimal_digits(src: &str) -> Option<(bool, BigInt, u64)>{
+        let (negative, src) = match src.strip_prefix('-'){
+            Some(rest) => (true, rest),
+            None => (false, src.strip_prefix('+').unwrap_or(src))
+        };
+        if src.is_empty() || !src.bytes().all(|b| b.is_ascii_digit() || b == b'.'){
+            return None;
+        }
+        let (whole, frac) = src.split_once('.').unwrap_or((src, ""));
+        Some((negative, BigInt::parse(&format!("{}{}", whole, frac))?, frac.len() as u64))
+    }
+
+    // The tightest f64 bounds of a decimal number, a single point when it is exact.
+    fn literal_bounds(src: &str) -> Result<(f64, f64), &'static str>{
+        let src = src.trim();
+        let x = parse_number(src.strip_prefix('+').unwrap_or(src))?;
+        if !x.is_finite(){
+            return Ok((x, x));
+        }
+        let (negative, digits, places) = match decimal_digits(src){
+            Some(parts) => parts,
+            None => return Ok((x.next_down(), x.next_up()))
+        };
+        let bits = x.abs().to_bits();
+        let (mantissa, exp) = match (bits >> 52) as i64{
+            0 => (bits, -1074),
+            e => ((bits & ((1 << 52) - 1)) | (1 << 52), e - 1075)
+        };
+        let parsed = &BigInt::from(mantissa) * &BigInt::from(10u64).pow(places);
+        let parsed = parsed.shifted_left(exp.max(0) as u64);
+        let written = digits.shifted_left((-exp).max(0) as u64);
+        let (lo, hi) = match parsed.cmp(&written){
+            Ordering::Equal => (x.abs(), x.abs()),
+            Ordering::Less => (x.abs(), x.abs().next_up()),
+            Ordering::Greater => (x.abs().next_down(), x.abs())
+        };
+        Ok(if negative {(-hi, -lo)} else {(lo, hi)})
+    }
+
+    // Closed interval [lo, hi] holding the true value, NaN ends when there is none.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Interval{
+        pub lo: f64,
+        pub hi: f64
+    }
+
+    impl Interval{
+        pub fn new(lo: f64, hi: f64) -> Interval{
+            Interval{lo, hi}
+        }
+
+        pub fn point(x: f64) -> Interval{
+            Interval{lo: x, hi: x}
+        }
+
+        pub fn entire() -> Interval{
+            Interval{lo: f64::NEG_INFINITY, hi: f64::INFINITY}
+        }
+
+        pub fn nan() -> Interval{
+            Interval{lo: f64::NAN, hi: f64::NAN}
+        }
+
+        // Widened by a step on each side, for library functions that are not correctly rounded.
+        fn widened(lo: f64, hi: f64) -> Interval{
+            Interval{lo: lo.next_down(), hi: hi.next_up()}
+        }
+
+        // "[lo, hi]", "x±dx" or a plain number.
+        pub fn parse_literal(src: &str) -> Result<Interval, &'static str>{
+            if let Some(inner) = src.strip_prefix('[').and_then(|s| s.strip_suffix(']')){
+                let (lo, hi) = inner.split_once(',').ok_or("Bad interval")?;
+                let (lo, hi) = (literal_bounds(lo)?.0, literal_bounds(hi)?.1);
+                if lo.is_nan() || hi.is_nan() || lo > hi{
+                    return Err("Bad interval");
+                }
+                return Ok(Interval{lo, hi});
+            }
+            if let Some((mid, radius)) = src.split_once('±'){
+                let (mid, radius) = (literal_bounds(mid)?, literal_bounds(radius)?.1);
+                return Ok(Interval{lo: sum(mid.0, -radius, false), hi: sum(mid.1, radius, true)});
+            }
+            let (lo, hi) = literal_bounds(src)?;
+            Ok(Interval{lo, hi})
+        }
+
+        pub fn is_nan(&self) -> bool{
+            self.lo.is_nan() || self.hi.is_nan()
+        }
+
+        pub fn is_point(&self) -> bool{
+            self.lo == self.hi
+        }
+
+        pub fn contains(&self, x: f64) -> bool{
+            self.lo <= x && x <= self.hi
+        }
+
+        pub fn width(&self) -> f64{
+            sum(self.hi, -self.lo, true)
+        }
+
+        pub fn midpoint(&self) -> f64{
+            self.lo / 2f64 + self.hi / 2f64
+        }
+
+        pub fn add(&self, other: &Interval) -> Interval{
+            Interval{lo: sum(self.lo, other.lo, false), hi: sum(self.hi, other.hi, true)}
+        }
+
+        pub fn sub(&self, other: &Interval) -> Interval{
+            Interval{lo: sum(self.lo, -other.hi, false), hi: sum(self.hi, -other.lo, true)}
+        }
+
+        pub fn neg(&self) -> Interval{
+            Interval{lo: -self.hi, hi: -self.lo}
+        }
+
+        pub fn mul(&self, other: &Interval) -> Interval{
+            if self.is_nan() || other.is_nan(){
+                return Interval::nan();
+            }
+            let ends = [(self.lo, other.lo), (self.lo, other.hi), (self.hi, other.lo), (self.hi, other.hi)];
+            Interval{
+                lo: ends.iter().map(|&(a, b)| product(a, b, false)).fold(f64::INFINITY, f64::min),
+                hi: ends.iter().map(|&(a, b)| product(a, b, true)).fold(f64::NEG_INFINITY, f64::max)
+            }
+        }
+
+        // A divisor straddling zero splits the result in two, the hull of both pieces is returned.
+        pub fn div(&self, other: &Interval) -> Result<Interval, EvalError>{
+            if self.is_nan() || other.is_nan(){
+                return Ok(Interval::nan());
+            }
+            if other.lo == 0f64 && other.hi == 0f64{
+                return Err(EvalError::DivisionByZero);
+            }
+            if other.lo > 0f64 || other.hi < 0f64{
+                let ends = [(self.lo, other.lo), (self.lo, other.hi), (self.hi, other.lo), (self.hi, other.hi)];
+                return Ok(Interval{
+                    lo: ends.iter().map(|&(a, b)| quotient(a, b, false)).fold(f64::INFINITY, f64::min),
+                    hi: ends.iter().map(|&(a, b)| quotient(a, b, true)).fold(f64::NEG_INFINITY, f64::max)
+                });
+            }
+            let (inf, neg_inf) = (f64::INFINITY, f64::NEG_INFINITY);
+            Ok(match (self.hi < 0f64, self.lo > 0f64, other.lo == 0f64, other.hi == 0f64){
+                (true, _, true, _) => Interval{lo: neg_inf, hi: quotient(self.hi, other.hi, true)},
+                (true, _, _, true) => Interval{lo: quotient(self.hi, other.lo, false), hi: inf},
+                (_, true, true, _) => Interval{lo: quotient(self.lo, other.hi, false), hi: inf},
+                (_, true, _, true) => Interval{lo: neg_inf, hi: quotient(self.lo, other.lo, true)},
+                _ => Interval::entire()
+            })
+        }
+
+        fn powi(&self, n: u64) -> Interval{
+            if !n.is_multiple_of(2) || self.lo >= 0f64{
+                Interval{lo: power(self.lo, n, false), hi: power(self.hi, n, true)}
+            }else if self.hi <= 0f64{
+                Interval{lo: power(self.hi, n, false), hi: power(self.lo, n, true)}
+            }else{
+                Interval{lo: 0f64, hi: power(self.hi.max(-self.lo), n, true)}
+            }
+        }
+
+        // Integer exponents are exact powers, anything else goes through exp(y ln x) on x >= 0.
+        pub fn pow(&self, other: &Interval) -> Result<Interval, EvalError>{
+            if self.is_nan() || other.is_nan(){
+                return Ok(Interval::nan());
+            }
+            if other.is_point() && other.lo.fract() == 0f64 && other.lo.abs() <= u32::MAX as f64{
+                let n = other.lo.abs() as u64;
+                return if other.lo < 0f64 {Interval::point(1f64).div(&self.powi(n))} else {Ok(self.powi(n))};
+            }
+            if self.hi < 0f64{
+                return Ok(Interval::nan());
+            }
+            let base = Interval{lo: self.lo.max(0f64), hi: self.hi};
+            Ok(other.mul(&base.ln()).exp())
+        }
+
+        pub fn exp(&self) -> Interval{
+            let out = Interval::widened(self.lo.exp(), self.hi.exp());
+            Interval{
+                lo: if self.lo == 0f64 {1f64} else {out.lo.max(0f64)},
+                hi: if self.hi == 0f64 {1f64} else {out.hi}
+            }
+        }
+
+        pub fn ln(&self) -> Interval{
+            if self.hi < 0f64{
+                return Interval::nan();
+            }
+            let lo = if self.lo <= 0f64 {f64::NEG_INFINITY} else {self.lo.ln()};
+            let out = Interval::widened(lo, self.hi.ln());
+            Interval{
+                lo: if self.lo == 1f64 {0f64} else {out.lo},
+                hi: if self.hi == 1f64 {0f64} else {out.hi}
+            }
+        }
+
+        pub fn sqrt(&self) -> Interval{
+            if self.hi < 0f64{
+                return Interval::nan();
+            }
+            Interval{lo: square_root(self.lo.max(0f64), false), hi: square_root(self.hi, true)}
+        }
+
+        pub fn abs(&self) -> Interval{
+            if self.lo >= 0f64{
+                *self
+            }else if self.hi <= 0f64{
+                self.neg()
+            }else{
+                Interval{lo: 0f64, hi: self.hi.max(-self.lo)}
+            }
+        }
+
+        // f has its maximum at peak + 2k pi and its minimum half a turn later.
+        fn periodic(&self, f: fn(f64) -> f64, peak: f64) -> Interval{
+            if !self.lo.is_finite() || !self.hi.is_finite() || self.width() >= 2f64 * PI{
+                return Interval{lo: -1f64, hi: 1f64};
+            }
+            let (a, b) = (f(self.lo), f(self.hi));
+            let mut out = Interval::widened(a.min(b), a.max(b));
+            if hits(self.lo, self.hi, peak, 2f64 * PI){
+                out.hi = 1f64;
+            }
+            if hits(self.lo, self.hi, peak + PI, 2f64 * PI){
+                out.lo = -1f64;
+            }
+            Interval{lo: out.lo.max(-1f64), hi: out.hi.min(1f64)}
+        }
+
+        pub fn evaluate(&self, func: Function) -> Interval{
+            if self.is_nan(){
+                return Interval::nan();
+            }
+            match func{
+                Function::Sin => self.periodic(f64::sin, PI / 2f64),
+                Function::Cos => self.periodic(f64::cos, 0f64),
+                Function::Tan => {
+                    if !self.lo.is_finite() || !self.hi.is_finite() || self.width() >= PI || hits(self.lo, self.hi, PI / 2f64, PI){
+                        Interval::entire()
+                    }else{
+                        Interval::widened(self.lo.tan(), self.hi.tan())
+                    }
+                },
+                Function::Exp => self.exp(),
+                Function::Ln => self.ln(),
+                Function::Sqrt => self.sqrt(),
+                Function::Abs => self.abs(),
+                Function::Neg => self.neg(),
+                Function::Fact => {
+                    let (lo, hi) = (self.lo.max(0f64).ceil(), self.hi.floor());
+                    if lo > hi {Interval::nan()} else {Interval{lo: factorial(lo, false), hi: factorial(hi, true)}}
+                },
+                Function::Binom | Function::Not | Function::Popcount | Function::Rotl | Function::Rotr => Interval::nan(),
+                Function::Re | Function::Conj => *self,
+                Function::Im => Interval::point(0f64),
+                Function::Arg => {
+                    let lo = if self.hi < 0f64 {PI} else {0f64};
+                    let hi = if self.lo < 0f64 {PI.next_up()} else {0f64};
+                    Interval{lo, hi}
+                }
+            }
+        }
+    }
+
+    impl fmt::Display for Interval{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            write!(f, "[{}, {}]", self.lo, self.hi)
+        }
+    }
+
+    impl Number for Interval{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            Interval::parse_literal(src)
+        }
+
+        fn zero() -> Self{
+            Interval::point(0f64)
+        }
+
+        fn one() -> Self{
+            Interval::point(1f64)
+        }
+
+        // Only a single point can bound a sum or product.
+        fn to_f64(&self) -> f64{
+            if self.is_point() {self.lo} else {f64::NAN}
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Interval::add(self, other))
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Interval::sub(self, other))
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Interval::mul(self, other))
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            Interval::div(self, other)
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Interval::pow(self, other)
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            Ok(self.evaluate(func))
+        }
+
+        // Binomials of two exact integers, computed exactly and rounded outward.
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            let whole = |x: &Interval| x.is_point() && x.lo >= 0f64 && x.lo.fract() == 0f64 && x.lo <= u32::MAX as f64;
+            if func != Function::Binom || !whole(self) || !whole(other){
+                return Ok(Interval::nan());
+            }
+            let exact = BigInt::binomial(self.lo as u64, other.lo as u64)?.to_f64();
+            Ok(if exact <= EXACT_INTEGER {Interval::point(exact)} else {Interval::widened(exact, exact)})
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use std::collections::HashMap;
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree_with;
+
+        fn evaluate(src: &str) -> Result<Interval, EvalError>{
+            parse_to_tree_with(src, Interval::parse_literal).unwrap().evaluate_number(&HashMap::new())
+        }
+
+        #[test]
+        fn decimal_literals_are_enclosed(){
+            let sum = evaluate("0.1 + 0.2").unwrap();
+            assert!(sum.lo < sum.hi && sum.contains(0.3));
+            assert!(evaluate("1 + 2").unwrap().is_point());
+        }
+
+        #[test]
+        fn bounds_are_parsed_in_every_spelling(){
+            assert_eq!(Interval::parse_literal("[1, 2]").unwrap(), Interval::new(1f64, 2f64));
+            assert_eq!(evaluate("2 ± 0.5").unwrap(), Interval::new(1.5, 2.5));
+            assert!(Interval::parse_literal("[2, 1]").is_err());
+        }
+
+        #[test]
+        fn ascii_plus_minus_is_plus_then_minus(){
+            assert_eq!(evaluate("1+-2").unwrap(), Interval::point(-1f64));
+        }
+
+        #[test]
+        fn dependency_widens_but_squares_do_not(){
+            assert_eq!(evaluate("[1, 2] - [1, 2]").unwrap(), Interval::new(-1f64, 1f64));
+            assert_eq!(evaluate("[-2, 3] ^ 2").unwrap(), Interval::new(0f64, 9f64));
+        }
+
+        #[test]
+        fn division_through_zero(){
+            assert!(matches!(evaluate("1 / 0"), Err(EvalError::DivisionByZero)));
+            assert_eq!(evaluate("1 / [-1, 1]").unwrap(), Interval::entire());
+            assert_eq!(evaluate("1 / [0, 2]").unwrap(), Interval::new(0.5, f64::INFINITY));
+        }
+    }
+}
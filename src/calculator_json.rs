@@ -0,0 +1,531 @@
+#[allow(dead_code)]
+pub mod json{
+    use std::{
+        fmt,
+        iter::Peekable,
+        str::CharIndices
+    };
+    use crate::calculator_model::model::{
+        Aggregate,
+        Function,
+        Node,
+        Operator,
+        Tree,
+        Value
+    };
+
+    // Nesting deeper than this is rejected instead of overflowing the stack.
+    const MAX_DEPTH: usize = 512;
+
+    #[derive(Debug)]
+    pub enum JsonError{
+        Syntax(usize, &'static str),
+        Shape(String, String)
+    }
+
+    impl fmt::Display for JsonError{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            match self{
+                JsonError::Syntax(pos, msg) => write!(f, "JSON syntax error at byte {}: {}", pos, msg),
+                JsonError::Shape(path, msg) => write!(f, "Malformed tree at {}: {}", path, msg)
+            }
+        }
+    }
+
+    pub enum Json{
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>)
+    }
+
+    impl Json{
+        pub fn kind(&self) -> &'static str{
+            match self{
+                Json::Null => "null",
+                Json::Bool(_) => "a boolean",
+                Json::Number(_) => "a number",
+                Json::String(_) => "a string",
+                Json::Array(_) => "an array",
+                Json::Object(_) => "an object"
+            }
+        }
+    }
+
+    struct Reader<'a>{
+        src: &'a str,
+        chars: Peekable<CharIndices<'a>>,
+        depth: usize
+    }
+
+    impl<'a> Reader<'a>{
+        fn pos(&mut self) -> usize{
+            let len = self.src.len();
+            self.chars.peek().map_or(len, |(i, _)| *i)
+        }
+
+        fn error<T>(&mut self, msg: &'static str) -> Result<T, JsonError>{
+            Err(JsonError::Syntax(self.pos(), msg))
+        }
+
+        fn skip_ws(&mut self){
+            while let Some((_, ' ' | '\t' | '\n' | '\r')) = self.chars.peek(){
+                self.chars.next();
+            }
+        }
+
+        fn expect(&mut self, c: char, msg: &'static str) -> Result<(), JsonError>{
+            self.skip_ws();
+            match self.chars.peek(){
+                Some((_, d)) if *d == c => {
+                    self.chars.next();
+                    Ok(())
+                },
+                _ => self.error(msg)
+            }
+        }
+
+        fn literal(&mut self, word: &str, value: Json) -> Result<Json, JsonError>{
+            for c in word.chars(){
+                match self.chars.next(){
+                    Some((_, d)) if d == c => (),
+                    _ => return self.error("invalid literal")
+                }
+            }
+            Ok(value)
+        }
+
+        fn value(&mut self) -> Result<Json, JsonError>{
+            self.skip_ws();
+            match self.chars.peek().map(|(_, c)| *c){
+                Some('{') => self.object(),
+                Some('[') => self.array(),
+                Some('"') => Ok(Json::String(self.string()?)),
+                Some('t') => self.literal("true", Json::Bool(true)),
+                Some('f') => self.literal("false", Json::Bool(false)),
+                Some('n') => self.literal("null", Json::Null),
+                Some('-' | '0'..='9') => self.number(),
+                Some(_) => self.error("unexpected character"),
+                None => self.error("unexpected end of input")
+            }
+        }
+
+        fn enter(&mut self) -> Result<(), JsonError>{
+            self.depth += 1;
+            if self.depth > MAX_DEPTH{
+                return self.error("nesting too deep");
+            }
+            self.chars.next();
+            Ok(())
+        }
+
+        fn object(&mut self) -> Result<Json, JsonError>{
+            self.enter()?;
+            let mut members = Vec::new();
+            self.skip_ws();
+            if let Some((_, '}')) = self.chars.peek(){
+                self.chars.next();
+                self.depth -= 1;
+                return Ok(Json::Object(members));
+            }
+            loop{
+                self.skip_ws();
+                match self.chars.peek(){
+                    Some((_, '"')) => (),
+                    _ => return self.error("expected a key")
+                }
+                let key = self.string()?;
+                self.expect(':', "expected ':'")?;
+                let value = self.value()?;
+                members.push((key, value));
+                self.skip_ws();
+                match self.chars.next(){
+                    Some((_, ',')) => continue,
+                    Some((_, '}')) => break,
+                    Some((i, _)) => return Err(JsonError::Syntax(i, "expected ',' or '}'")),
+                    None => return self.error("expected ',' or '}'")
+                }
+            }
+            self.depth -= 1;
+            Ok(Json::Object(members))
+        }
+
+        fn array(&mut self) -> Result<Json, JsonError>{
+            self.enter()?;
+            let mut items = Vec::new();
+            self.skip_ws();
+            if let Some((_, ']')) = self.chars.peek(){
+                self.chars.next();
+                self.depth -= 1;
+                return Ok(Json::Array(items));
+            }
+            loop{
+                items.push(self.value()?);
+                self.skip_ws();
+                match self.chars.next(){
+                    Some((_, ',')) => continue,
+                    Some((_, ']')) => break,
+                    Some((i, _)) => return Err(JsonError::Syntax(i, "expected ',' or ']'")),
+                    None => return self.error("expected ',' or ']'")
+                }
+            }
+            self.depth -= 1;
+            Ok(Json::Array(items))
+        }
+
+        fn hex4(&mut self) -> Result<u32, JsonError>{
+            let mut code = 0;
+            for _ in 0..4{
+                match self.chars.next().and_then(|(_, c)| c.to_digit(16)){
+                    Some(d) => code = code * 16 + d,
+                    None => return self.error("invalid unicode escape")
+                }
+            }
+            Ok(code)
+        }
+
+        fn string(&mut self) -> Result<String, JsonError>{
+            self.chars.next();
+            let mut out = String::new();
+            loop{
+                match self.chars.next(){
+                    Some((_, '"')) => return Ok(out),
+                    Some((_, '\\')) => {
+                        match self.chars.next(){
+                            Some((_, '"')) => out.push('"'),
+                            Some((_, '\\')) => out.push('\\'),
+                            Some((_, '/')) => out.push('/'),
+                            Some((_, 'b')) => out.push('\u{8}'),
+                            Some((_, 'f')) => out.push('\u{c}'),
+                            Some((_, 'n')) => out.push('\n'),
+                            Some((_, 'r')) => out.push('\r'),
+                            Some((_, 't')) => out.push('\t'),
+                            Some((_, 'u')) => {
+                                let mut code = self.hex4()?;
+                                if (0xD800..0xDC00).contains(&code){
+                                    if !matches!((self.chars.next(), self.chars.next()), (Some((_, '\\')), Some((_, 'u')))){
+                                        return self.error("expected low surrogate");
+                                    }
+                                    let low = self.hex4()?;
+                                    if !(0xDC00..0xE000).contains(&low){
+                                        return self.error("invalid low surrogate");
+                                    }
+                                    code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                                }
+                                match char::from_u32(code){
+                                    Some(c) => out.push(c),
+                                    None => return self.error("invalid unicode escape")
+                                }
+                            },
+                            Some((i, _)) => return Err(JsonError::Syntax(i, "invalid escape")),
+                            None => return self.error("unterminated string")
+                        }
+                    },
+                    Some((_, c)) if (c as u32) < 0x20 => return self.error("control character in string"),
+                    Some((_, c)) => out.push(c),
+                    None => return self.error("unterminated string")
+                }
+            }
+        }
+
+        fn number(&mut self) -> Result<Json, JsonError>{
+            let start = self.pos();
+            while let Some((_, '-' | '+' | '.' | 'e' | 'E' | '0'..='9')) = self.chars.peek(){
+                self.chars.next();
+            }
+            let end = self.pos();
+            let text = &self.src[start..end];
+            let digits = text.strip_prefix('-').unwrap_or(text);
+            if digits.starts_with('0') && digits.len() > 1 && !digits[1..].starts_with(['.', 'e', 'E']){
+                return Err(JsonError::Syntax(start, "leading zero in number"));
+            }
+            if !digits.starts_with(|c: char| c.is_ascii_digit()) || digits.contains(".e") || digits.contains(".E") || digits.ends_with('.'){
+                return Err(JsonError::Syntax(start, "invalid number"));
+            }
+            match text.parse::<f64>(){
+                Ok(num) => Ok(Json::Number(num)),
+                Err(_) => Err(JsonError::Syntax(start, "invalid number"))
+            }
+        }
+    }
+
+    fn shape<T>(path: &str, msg: String) -> Result<T, JsonError>{
+        Err(JsonError::Shape(path.to_string(), msg))
+    }
+
+    fn member<'j>(members: &'j [(String, Json)], key: &str, path: &str) -> Result<&'j Json, JsonError>{
+        match members.iter().find(|(k, _)| k == key){
+            Some((_, value)) => Ok(value),
+            None => shape(path, format!("missing \"{}\"", key))
+        }
+    }
+
+    fn to_node(json: &Json, path: &str) -> Result<Box<Node<Value>>, JsonError>{
+        let members = match json{
+            Json::Object(members) => members,
+            other => return shape(path, format!("expected an object, found {}", other.kind()))
+        };
+        for (i, (key, _)) in members.iter().enumerate(){
+            if members[..i].iter().any(|(k, _)| k == key){
+                return shape(path, format!("duplicate key \"{}\"", key));
+            }
+        }
+        let keys: Vec<&str> = members.iter().map(|(k, _)| k.as_str()).collect();
+        let kinds: Vec<&str> = keys.iter().copied().filter(|k| ["num", "var", "op", "fn", "agg"].contains(k)).collect();
+        if kinds.len() != 1{
+            return shape(path, "expected exactly one of \"num\", \"var\", \"op\", \"fn\" or \"agg\"".to_string());
+        }
+        let (expected, value): (&[&str], Value) = match (kinds[0], member(members, kinds[0], path)?){
+            ("num", Json::Number(num)) => (&["num"], Value::Value(*num)),
+            ("num", Json::String(s)) if s == "NaN" => (&["num"], Value::Value(f64::NAN)),
+            ("num", Json::String(s)) if s == "inf" => (&["num"], Value::Value(f64::INFINITY)),
+            ("num", Json::String(s)) if s == "-inf" => (&["num"], Value::Value(f64::NEG_INFINITY)),
+            ("var", Json::String(name)) => {
+                if !is_identifier(name){
+                    return shape(path, format!("\"{}\" is not a valid variable name", name));
+                }
+                (&["var"], Value::Variable(name.clone()))
+            },
+            ("op", Json::String(symbol)) => {
+                match Operator::from_symbol(symbol){
+                    Some(op) => (&["op", "left", "right"], Value::Operator(op)),
+                    None => return shape(path, format!("unknown operator \"{}\"", symbol))
+                }
+            },
+            ("fn", Json::String(name)) => {
+                match Function::from_name(name){
+                    Some(func) if func.arity() == 2 => (&["fn", "left", "right"], Value::Function(func)),
+                    Some(func) => (&["fn", "arg"], Value::Function(func)),
+                    None => return shape(path, format!("unknown function \"{}\"", name))
+                }
+            },
+            ("agg", Json::String(name)) => {
+                match Aggregate::from_name(name){
+                    Some(agg) => (&["agg", "bind", "from", "to", "body"], Value::Aggregate(agg)),
+                    None => return shape(path, format!("unknown aggregate \"{}\"", name))
+                }
+            },
+            ("num", other) => return shape(path, format!("\"num\" must be a number, found {}", other.kind())),
+            (kind, other) => return shape(path, format!("\"{}\" must be a string, found {}", kind, other.kind()))
+        };
+        if let Some(key) = keys.iter().find(|k| !expected.contains(k)){
+            return shape(path, format!("unexpected key \"{}\"", key));
+        }
+        match value{
+            Value::Function(func) if func.arity() == 1 => {
+                let arg = to_node(member(members, "arg", path)?, &format!("{}.arg", path))?;
+                Ok(Node::with_children(value, Some(arg), None))
+            },
+            Value::Operator(_) | Value::Function(_) => {
+                let left = to_node(member(members, "left", path)?, &format!("{}.left", path))?;
+                let right = to_node(member(members, "right", path)?, &format!("{}.right", path))?;
+                Ok(Node::with_children(value, Some(left), Some(right)))
+            },
+            Value::Aggregate(_) => {
+                let var = match member(members, "bind", path)?{
+                    Json::String(name) if is_identifier(name) => name.clone(),
+                    Json::String(name) => return shape(path, format!("\"{}\" is not a valid variable name", name)),
+                    other => return shape(path, format!("\"bind\" must be a string, found {}", other.kind()))
+                };
+                let from = to_node(member(members, "from", path)?, &format!("{}.from", path))?;
+                let to = to_node(member(members, "to", path)?, &format!("{}.to", path))?;
+                let body = to_node(member(members, "body", path)?, &format!("{}.body", path))?;
+                let range = Node::with_children(Value::Range(var), Some(from), Some(to));
+                Ok(Node::with_children(value, Some(body), Some(range)))
+            },
+            _ => Ok(Node::with_children(value, None, None))
+        }
+    }
+
+    fn is_identifier(name: &str) -> bool{
+        match name.chars().next(){
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            _ => false
+        }
+    }
+
+    // Any JSON document, for callers with their own shape to check.
+    pub fn parse_value(src: &str) -> Result<Json, JsonError>{
+        let mut reader = Reader{
+            src,
+            chars: src.char_indices().peekable(),
+            depth: 0
+        };
+        let json = reader.value()?;
+        reader.skip_ws();
+        if reader.chars.peek().is_some(){
+            return reader.error("trailing characters");
+        }
+        Ok(json)
+    }
+
+    pub fn parse_json(src: &str) -> Result<Tree<Value>, JsonError>{
+        Ok(Tree::from_root(to_node(&parse_value(src)?, "$")?))
+    }
+
+    fn write_string(out: &mut String, s: &str){
+        out.push('"');
+        for c in s.chars(){
+            match c{
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c)
+            }
+        }
+        out.push('"');
+    }
+
+    fn write_operand(out: &mut String, node: Option<&Node<Value>>){
+        match node{
+            Some(n) => write_json(out, n),
+            None => out.push_str("{\"num\":0.0}")
+        }
+    }
+
+    fn write_json(out: &mut String, node: &Node<Value>){
+        match &node.value{
+            Some(Value::Value(num)) => {
+                if num.is_nan(){
+                    out.push_str("{\"num\":\"NaN\"}");
+                }else if num.is_infinite(){
+                    out.push_str(if *num > 0f64 {"{\"num\":\"inf\"}"} else {"{\"num\":\"-inf\"}"});
+                }else{
+                    out.push_str(&format!("{{\"num\":{:?}}}", num));
+                }
+            },
+            Some(Value::Variable(name)) => {
+                out.push_str("{\"var\":");
+                write_string(out, name);
+                out.push('}');
+            },
+            Some(Value::Operator(op)) => {
+                out.push_str(&format!("{{\"op\":\"{}\",\"left\":", op));
+                write_operand(out, node.left_node());
+                out.push_str(",\"right\":");
+                write_operand(out, node.right_node());
+                out.push('}');
+            },
+            Some(Value::Function(func)) if func.arity() == 2 => {
+                out.push_str(&format!("{{\"fn\":\"{}\",\"left\":", func));
+                write_operand(out, node.left_node());
+                out.push_str(",\"right\":");
+                write_operand(out, node.right_node());
+                out.push('}');
+            },
+            Some(Value::Function(func)) => {
+                out.push_str(&format!("{{\"fn\":\"{}\",\"arg\":", func));
+                write_operand(out, node.left_node());
+                out.push('}');
+            },
+            Some(Value::Aggregate(agg)) => {
+                out.push_str(&format!("{{\"agg\":\"{}\",\"bind\":", agg));
+                let range = node.right_node();
+                match range.and_then(|r| r.value.as_ref()){
+                    Some(Value::Range(name)) => write_string(out, name),
+                    _ => out.push_str("null")
+                }
+                out.push_str(",\"from\":");
+                write_operand(out, range.and_then(|r| r.left_node()));
+                out.push_str(",\"to\":");
+                write_operand(out, range.and_then(|r| r.right_node()));
+                out.push_str(",\"body\":");
+                write_operand(out, node.left_node());
+                out.push('}');
+            },
+            Some(Value::Range(name)) => write_string(out, name),
+            None => out.push_str("null")
+        }
+    }
+
+    impl Tree<Value>{
+        pub fn to_json(&self) -> String{
+            let mut out = String::new();
+            match self.root_node(){
+                Some(root) => write_json(&mut out, root),
+                None => out.push_str("null")
+            }
+            out
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree;
+
+        fn syntax(src: &str) -> Option<(usize, &'static str)>{
+            match parse_value(src){
+                Err(JsonError::Syntax(pos, msg)) => Some((pos, msg)),
+                _ => None
+            }
+        }
+
+        fn shape_error(src: &str) -> Option<(String, String)>{
+            match parse_json(src){
+                Err(JsonError::Shape(path, msg)) => Some((path, msg)),
+                _ => None
+            }
+        }
+
+        #[test]
+        fn trees_round_trip(){
+            for src in ["1 - (2 - x_1) * 0.1", "-sin(x)^2 + 3!", "binom(n, 2) / 0.001", "integrate(x^2, x, 0, 1) + sum(k, k, 1, n)"]{
+                let json = parse_to_tree(src).unwrap().to_json();
+                let tree = parse_json(&json).unwrap();
+                assert_eq!(tree.to_json(), json, "{}", src);
+                assert_eq!(tree.to_string(), src);
+            }
+            let json = "{\"op\":\"+\",\"left\":{\"num\":\"inf\"},\"right\":{\"num\":\"NaN\"}}";
+            assert_eq!(parse_json(json).unwrap().to_json(), json);
+        }
+
+        #[test]
+        fn writes_one_object_per_node(){
+            assert_eq!(parse_to_tree("x * 2").unwrap().to_json(), "{\"op\":\"*\",\"left\":{\"var\":\"x\"},\"right\":{\"num\":2.0}}");
+            assert_eq!(parse_to_tree("sqrt(2)").unwrap().to_json(), "{\"fn\":\"sqrt\",\"arg\":{\"num\":2.0}}");
+            assert_eq!(Tree::<Value>::new().to_json(), "null");
+        }
+
+        #[test]
+        fn reads_any_json(){
+            let tree = parse_json(" { \"fn\" : \"binom\" , \"right\" : {\"num\": 2}, \"left\": {\"num\": 5e0} } ").unwrap();
+            assert_eq!(tree.to_string(), "binom(5, 2)");
+            assert!(matches!(parse_value("[true, false, null, \"\\u00e9\\ud83d\\ude00\"]"), Ok(Json::Array(items)) if items.len() == 4));
+            assert!(matches!(parse_value("\"a\\/b\\n\""), Ok(Json::String(s)) if s == "a/b\n"));
+            assert!(matches!(parse_value("-0.5e-3"), Ok(Json::Number(num)) if num == -0.0005));
+        }
+
+        #[test]
+        fn syntax_errors_carry_the_byte_offset(){
+            assert_eq!(syntax(""), Some((0, "unexpected end of input")));
+            assert_eq!(syntax("{\"a\" 1}"), Some((5, "expected ':'")));
+            assert_eq!(syntax("[1 2]"), Some((3, "expected ',' or ']'")));
+            assert_eq!(syntax("01"), Some((0, "leading zero in number")));
+            assert_eq!(syntax("1."), Some((0, "invalid number")));
+            assert_eq!(syntax("nul"), Some((3, "invalid literal")));
+            assert_eq!(syntax("\"\\x\""), Some((2, "invalid escape")));
+            assert_eq!(syntax("{\"a\":1"), Some((6, "expected ',' or '}'")));
+            assert_eq!(syntax("\"\\ud83d\""), Some((8, "expected low surrogate")));
+            assert_eq!(syntax("\"abc"), Some((4, "unterminated string")));
+            assert_eq!(syntax("1 2"), Some((2, "trailing characters")));
+            assert_eq!(syntax(&"[".repeat(MAX_DEPTH + 1)), Some((MAX_DEPTH, "nesting too deep")));
+        }
+
+        #[test]
+        fn shape_errors_carry_the_path(){
+            let error = |path: &str, msg: &str| Some((path.to_string(), msg.to_string()));
+            assert_eq!(shape_error("[]"), error("$", "expected an object, found an array"));
+            assert_eq!(shape_error("{\"op\":\"+\",\"left\":{\"num\":1}}"), error("$", "missing \"right\""));
+            assert_eq!(shape_error("{\"op\":\"+\",\"left\":{\"num\":1},\"right\":{\"var\":\"2x\"}}"), error("$.right", "\"2x\" is not a valid variable name"));
+            assert_eq!(shape_error("{\"fn\":\"sin\",\"arg\":{\"num\":\"1\"}}"), error("$.arg", "\"num\" must be a number, found a string"));
+            assert_eq!(shape_error("{\"num\":1,\"var\":\"x\"}"), error("$", "expected exactly one of \"num\", \"var\", \"op\", \"fn\" or \"agg\""));
+            assert_eq!(shape_error("{\"num\":1,\"num\":2}"), error("$", "duplicate key \"num\""));
+            assert_eq!(shape_error("{\"num\":1,\"left\":{\"num\":2}}"), error("$", "unexpected key \"left\""));
+            assert_eq!(shape_error("{\"op\":\"%\"}"), error("$", "unknown operator \"%\""));
+            assert_eq!(shape_error("{\"agg\":\"sum\",\"bind\":1,\"from\":{\"num\":1},\"to\":{\"num\":2},\"body\":{\"num\":3}}"), error("$", "\"bind\" must be a string, found a number"));
+        }
+    }
+}
@@ -0,0 +1,402 @@
+#[allow(dead_code)]
+pub mod markup{
+    use crate::calculator_infix::infix;
+    use crate::calculator_model::model::{
+        Aggregate,
+        Function,
+        Node,
+        Operator,
+        Tree,
+        Value
+    };
+
+    // Fractions and exponents are already grouped by the markup, so they need fewer parentheses than infix text.
+    fn operand_parens(parent: &Operator, child: &Node<Value>, is_right: bool) -> bool{
+        match (parent, &child.value){
+            (Operator::Div, _) => false,
+            (Operator::Pow, _) if is_right => false,
+            (Operator::Pow, Some(Value::Function(_))) => true,
+            (Operator::Pow, Some(Value::Operator(Operator::Div))) => true,
+            (_, Some(Value::Operator(Operator::Div))) => false,
+            (_, Some(Value::Function(Function::Neg))) if is_right => true,
+            _ => infix::needs_parens(parent, child, is_right)
+        }
+    }
+
+    fn neg_parens(arg: &Node<Value>) -> bool{
+        match &arg.value{
+            Some(Value::Operator(Operator::Div)) => false,
+            _ => infix::binding(arg) < 7
+        }
+    }
+
+    // Bound variable, lower and upper bound of an aggregate node.
+    fn range_of(node: &Node<Value>) -> (&str, Option<&Node<Value>>, Option<&Node<Value>>){
+        match node.right_node(){
+            Some(range) => {
+                match &range.value{
+                    Some(Value::Range(var)) => (var, range.left_node(), range.right_node()),
+                    _ => ("", None, None)
+                }
+            },
+            None => ("", None, None)
+        }
+    }
+
+    fn body_parens(body: &Node<Value>) -> bool{
+        infix::binding(body) < 6
+    }
+
+    fn latex_var(out: &mut String, name: &str){
+        if name.chars().count() == 1{
+            out.push_str(name);
+        }else{
+            out.push_str(&format!("\\mathit{{{}}}", name.replace('_', "\\_")));
+        }
+    }
+
+    fn latex_operand(out: &mut String, node: Option<&Node<Value>>, parens: bool){
+        match node{
+            Some(n) if parens => {
+                out.push_str("\\left(");
+                latex_rec(out, n);
+                out.push_str("\\right)");
+            },
+            Some(n) => latex_rec(out, n),
+            None => out.push('0')
+        }
+    }
+
+    fn latex_rec(out: &mut String, node: &Node<Value>){
+        let left = node.left_node();
+        let right = node.right_node();
+        match &node.value{
+            Some(Value::Value(num)) => out.push_str(&num.to_string()),
+            Some(Value::Variable(name)) | Some(Value::Range(name)) => latex_var(out, name),
+            Some(Value::Operator(op)) => {
+                let left_parens = left.is_some_and(|n| operand_parens(op, n, false));
+                let right_parens = right.is_some_and(|n| operand_parens(op, n, true));
+                match op{
+                    Operator::Div => {
+                        out.push_str("\\frac{");
+                        latex_operand(out, left, false);
+                        out.push_str("}{");
+                        latex_operand(out, right, false);
+                        out.push('}');
+                    },
+                    Operator::Pow => {
+                        out.push('{');
+                        latex_operand(out, left, left_parens);
+                        out.push_str("}^{");
+                        latex_operand(out, right, false);
+                        out.push('}');
+                    },
+                    _ => {
+                        latex_operand(out, left, left_parens);
+                        match op{
+                            Operator::Mult => out.push_str(" \\cdot "),
+                            Operator::And => out.push_str(" \\mathbin{\\&} "),
+                            Operator::Or => out.push_str(" \\mathbin{|} "),
+                            Operator::Xor => out.push_str(" \\oplus "),
+                            Operator::Shl => out.push_str(" \\ll "),
+                            Operator::Shr => out.push_str(" \\gg "),
+                            _ => out.push_str(&format!(" {} ", op))
+                        }
+                        latex_operand(out, right, right_parens);
+                    }
+                }
+            },
+            Some(Value::Function(func)) => {
+                match func{
+                    Function::Neg => {
+                        out.push('-');
+                        latex_operand(out, left, left.is_some_and(neg_parens));
+                    },
+                    Function::Sqrt => {
+                        out.push_str("\\sqrt{");
+                        latex_operand(out, left, false);
+                        out.push('}');
+                    },
+                    Function::Abs => {
+                        out.push_str("\\left|");
+                        latex_operand(out, left, false);
+                        out.push_str("\\right|");
+                    },
+                    Function::Exp => {
+                        out.push_str("e^{");
+                        latex_operand(out, left, false);
+                        out.push('}');
+                    },
+                    Function::Fact => {
+                        latex_operand(out, left, left.is_some_and(|n| infix::binding(n) < 9));
+                        out.push('!');
+                    },
+                    Function::Conj => {
+                        out.push_str("\\overline{");
+                        latex_operand(out, left, false);
+                        out.push('}');
+                    },
+                    Function::Re | Function::Im => {
+                        out.push_str(&format!("\\operatorname{{{}}}", if *func == Function::Re {"Re"} else {"Im"}));
+                        latex_operand(out, left, true);
+                    },
+                    Function::Binom => {
+                        out.push_str("\\binom{");
+                        latex_operand(out, left, false);
+                        out.push_str("}{");
+                        latex_operand(out, right, false);
+                        out.push('}');
+                    },
+                    Function::Not => {
+                        out.push_str("\\lnot ");
+                        latex_operand(out, left, left.is_some_and(neg_parens));
+                    },
+                    Function::Popcount => {
+                        out.push_str("\\operatorname{popcount}");
+                        latex_operand(out, left, true);
+                    },
+                    Function::Rotl | Function::Rotr => {
+                        out.push_str(&format!("\\operatorname{{{}}}\\left(", func));
+                        latex_operand(out, left, false);
+                        out.push_str(", ");
+                        latex_operand(out, right, false);
+                        out.push_str("\\right)");
+                    },
+                    _ => {
+                        out.push_str(&format!("\\{}", func));
+                        latex_operand(out, left, true);
+                    }
+                }
+            },
+            Some(Value::Aggregate(agg)) => {
+                let (var, from, to) = range_of(node);
+                match agg{
+                    Aggregate::Integrate => out.push_str("\\int_{"),
+                    Aggregate::Sum => out.push_str("\\sum_{"),
+                    Aggregate::Product => out.push_str("\\prod_{")
+                }
+                if *agg != Aggregate::Integrate{
+                    latex_var(out, var);
+                    out.push_str(" = ");
+                }
+                latex_operand(out, from, false);
+                out.push_str("}^{");
+                latex_operand(out, to, false);
+                out.push_str("} ");
+                latex_operand(out, left, left.is_some_and(body_parens));
+                if *agg == Aggregate::Integrate{
+                    out.push_str(" \\, d");
+                    latex_var(out, var);
+                }
+            },
+            None => out.push_str("\\mathrm{None}")
+        }
+    }
+
+    fn mathml_operand(out: &mut String, node: Option<&Node<Value>>, parens: bool){
+        match node{
+            Some(n) if parens => {
+                out.push_str("<mrow><mo>(</mo>");
+                mathml_rec(out, n);
+                out.push_str("<mo>)</mo></mrow>");
+            },
+            Some(n) => mathml_rec(out, n),
+            None => out.push_str("<mn>0</mn>")
+        }
+    }
+
+    fn mathml_rec(out: &mut String, node: &Node<Value>){
+        let left = node.left_node();
+        let right = node.right_node();
+        match &node.value{
+            Some(Value::Value(num)) => {
+                if num.is_sign_negative(){
+                    out.push_str(&format!("<mrow><mo>&#x2212;</mo><mn>{}</mn></mrow>", -num));
+                }else{
+                    out.push_str(&format!("<mn>{}</mn>", num));
+                }
+            },
+            Some(Value::Variable(name)) | Some(Value::Range(name)) => out.push_str(&format!("<mi>{}</mi>", name)),
+            Some(Value::Operator(op)) => {
+                let left_parens = left.is_some_and(|n| operand_parens(op, n, false));
+                let right_parens = right.is_some_and(|n| operand_parens(op, n, true));
+                match op{
+                    Operator::Div => {
+                        out.push_str("<mfrac><mrow>");
+                        mathml_operand(out, left, false);
+                        out.push_str("</mrow><mrow>");
+                        mathml_operand(out, right, false);
+                        out.push_str("</mrow></mfrac>");
+                    },
+                    Operator::Pow => {
+                        out.push_str("<msup><mrow>");
+                        mathml_operand(out, left, left_parens);
+                        out.push_str("</mrow><mrow>");
+                        mathml_operand(out, right, false);
+                        out.push_str("</mrow></msup>");
+                    },
+                    _ => {
+                        out.push_str("<mrow>");
+                        mathml_operand(out, left, left_parens);
+                        match op{
+                            Operator::Minus => out.push_str("<mo>&#x2212;</mo>"),
+                            Operator::Mult => out.push_str("<mo>&#x22C5;</mo>"),
+                            Operator::And => out.push_str("<mo>&amp;</mo>"),
+                            Operator::Xor => out.push_str("<mo>&#x2295;</mo>"),
+                            Operator::Shl => out.push_str("<mo>&lt;&lt;</mo>"),
+                            Operator::Shr => out.push_str("<mo>&gt;&gt;</mo>"),
+                            _ => out.push_str(&format!("<mo>{}</mo>", op))
+                        }
+                        mathml_operand(out, right, right_parens);
+                        out.push_str("</mrow>");
+                    }
+                }
+            },
+            Some(Value::Function(func)) => {
+                match func{
+                    Function::Neg => {
+                        out.push_str("<mrow><mo>&#x2212;</mo>");
+                        mathml_operand(out, left, left.is_some_and(neg_parens));
+                        out.push_str("</mrow>");
+                    },
+                    Function::Sqrt => {
+                        out.push_str("<msqrt>");
+                        mathml_operand(out, left, false);
+                        out.push_str("</msqrt>");
+                    },
+                    Function::Abs => {
+                        out.push_str("<mrow><mo>|</mo>");
+                        mathml_operand(out, left, false);
+                        out.push_str("<mo>|</mo></mrow>");
+                    },
+                    Function::Exp => {
+                        out.push_str("<msup><mi>e</mi><mrow>");
+                        mathml_operand(out, left, false);
+                        out.push_str("</mrow></msup>");
+                    },
+                    Function::Fact => {
+                        out.push_str("<mrow>");
+                        mathml_operand(out, left, left.is_some_and(|n| infix::binding(n) < 9));
+                        out.push_str("<mo>!</mo></mrow>");
+                    },
+                    Function::Conj => {
+                        out.push_str("<mover accent=\"true\"><mrow>");
+                        mathml_operand(out, left, false);
+                        out.push_str("</mrow><mo>&#x00AF;</mo></mover>");
+                    },
+                    Function::Binom => {
+                        out.push_str("<mrow><mo>(</mo><mfrac linethickness=\"0\"><mrow>");
+                        mathml_operand(out, left, false);
+                        out.push_str("</mrow><mrow>");
+                        mathml_operand(out, right, false);
+                        out.push_str("</mrow></mfrac><mo>)</mo></mrow>");
+                    },
+                    Function::Not => {
+                        out.push_str("<mrow><mo>&#x00AC;</mo>");
+                        mathml_operand(out, left, left.is_some_and(neg_parens));
+                        out.push_str("</mrow>");
+                    },
+                    Function::Rotl | Function::Rotr => {
+                        out.push_str(&format!("<mrow><mi>{}</mi><mo>&#x2061;</mo><mrow><mo>(</mo>", func));
+                        mathml_operand(out, left, false);
+                        out.push_str("<mo>,</mo>");
+                        mathml_operand(out, right, false);
+                        out.push_str("<mo>)</mo></mrow></mrow>");
+                    },
+                    _ => {
+                        out.push_str(&format!("<mrow><mi>{}</mi><mo>&#x2061;</mo>", func));
+                        mathml_operand(out, left, true);
+                        out.push_str("</mrow>");
+                    }
+                }
+            },
+            Some(Value::Aggregate(agg)) => {
+                let (var, from, to) = range_of(node);
+                match agg{
+                    Aggregate::Integrate => out.push_str("<mrow><msubsup><mo>&#x222B;</mo><mrow>"),
+                    Aggregate::Sum => out.push_str(&format!("<mrow><munderover><mo>&#x2211;</mo><mrow><mi>{}</mi><mo>=</mo>", var)),
+                    Aggregate::Product => out.push_str(&format!("<mrow><munderover><mo>&#x220F;</mo><mrow><mi>{}</mi><mo>=</mo>", var))
+                }
+                mathml_operand(out, from, false);
+                out.push_str("</mrow><mrow>");
+                mathml_operand(out, to, false);
+                match agg{
+                    Aggregate::Integrate => out.push_str("</mrow></msubsup>"),
+                    _ => out.push_str("</mrow></munderover>")
+                }
+                mathml_operand(out, left, left.is_some_and(body_parens));
+                if *agg == Aggregate::Integrate{
+                    out.push_str(&format!("<mo>&#x2062;</mo><mi>d</mi><mi>{}</mi>", var));
+                }
+                out.push_str("</mrow>");
+            },
+            None => out.push_str("<mi>None</mi>")
+        }
+    }
+
+    impl Tree<Value>{
+        pub fn to_latex(&self) -> String{
+            let mut out = String::new();
+            if let Some(root) = self.root_node(){
+                latex_rec(&mut out, root);
+            }
+            out
+        }
+
+        pub fn to_mathml(&self) -> String{
+            let mut out = String::from("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">");
+            if let Some(root) = self.root_node(){
+                mathml_rec(&mut out, root);
+            }
+            out.push_str("</math>");
+            out
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use crate::calculator_model::model::parse_to_tree;
+
+        fn latex(src: &str) -> String{
+            parse_to_tree(src).unwrap().to_latex()
+        }
+
+        fn mathml(src: &str) -> String{
+            let out = parse_to_tree(src).unwrap().to_mathml();
+            let body = out.strip_prefix("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">").unwrap();
+            body.strip_suffix("</math>").unwrap().to_string()
+        }
+
+        #[test]
+        fn latex_layout(){
+            assert_eq!(latex("(1 + x) / 2"), "\\frac{1 + x}{2}");
+            assert_eq!(latex("x^(2 + y)"), "{x}^{2 + y}");
+            assert_eq!(latex("1 - (2 - 3)"), "1 - \\left(2 - 3\\right)");
+            assert_eq!(latex("sqrt(x) * -y"), "\\sqrt{x} \\cdot \\left(-y\\right)");
+            assert_eq!(latex("3! - binom(n, k)"), "3! - \\binom{n}{k}");
+            assert_eq!(latex("integrate(x^2, x, 0, 1)"), "\\int_{0}^{1} {x}^{2} \\, dx");
+            assert_eq!(latex("sum(k^2, k, 1, n)"), "\\sum_{k = 1}^{n} {k}^{2}");
+        }
+
+        #[test]
+        fn latex_escapes_names_and_symbols(){
+            assert_eq!(latex("x_1 + alpha"), "\\mathit{x\\_1} + \\mathit{alpha}");
+            assert_eq!(latex("2 & 3"), "2 \\mathbin{\\&} 3");
+        }
+
+        #[test]
+        fn mathml_layout(){
+            assert_eq!(mathml("(1 + x) / 2"), "<mfrac><mrow><mrow><mn>1</mn><mo>+</mo><mi>x</mi></mrow></mrow><mrow><mn>2</mn></mrow></mfrac>");
+            assert_eq!(mathml("sqrt(x) * -y"),
+                "<mrow><msqrt><mi>x</mi></msqrt><mo>&#x22C5;</mo><mrow><mo>(</mo><mrow><mo>&#x2212;</mo><mi>y</mi></mrow><mo>)</mo></mrow></mrow>");
+            assert!(mathml("integrate(x^2, x, 0, 1)").starts_with("<mrow><msubsup><mo>&#x222B;</mo><mrow><mn>0</mn></mrow><mrow><mn>1</mn></mrow></msubsup>"));
+            assert!(mathml("binom(n, k)").contains("<mfrac linethickness=\"0\">"));
+        }
+
+        #[test]
+        fn mathml_escapes_markup(){
+            assert_eq!(mathml("x_1 & 3"), "<mrow><mi>x_1</mi><mo>&amp;</mo><mn>3</mn></mrow>");
+            assert!(!mathml("a << b").contains("<<"));
+        }
+    }
+}
@@ -1,25 +1,47 @@
 #[allow(dead_code)]
 pub mod model{
+    use crate::calculator_calculus::calculus::{
+        self,
+        Quadrature
+    };
+    use crate::calculator_number::number::Number;
     use std::{
         boxed::Box,
+        collections::HashMap,
         fmt,
+        io,
+        iter::Peekable,
         ptr::NonNull,
+        str::Chars,
         time    
     };
 
+    pub type Env = HashMap<String, f64>;
+
     #[derive(Clone)]
-    pub enum Value{
-        Value(f64),
-        Operator(Operator)
+    #[allow(clippy::enum_variant_names)]
+    pub enum Value<N = f64>{
+        Value(N),
+        Operator(Operator),
+        Variable(String),
+        Function(Function),
+        // Body in the left child, the Range it runs over in the right child.
+        Aggregate(Aggregate),
+        // Bound variable of an Aggregate, lower bound left and upper bound right.
+        Range(String)
     }
 
-    impl fmt::Display for Value{
+    impl<N: fmt::Display> fmt::Display for Value<N>{
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
             match self{
                 Self::Value(val) => write!(f,"{}",val),
                 Self::Operator(op) => {
                     write!(f,"{}",op)
-                }
+                },
+                Self::Variable(name) => write!(f,"{}",name),
+                Self::Function(func) => write!(f,"{}",func),
+                Self::Aggregate(agg) => write!(f,"{}",agg),
+                Self::Range(name) => write!(f,"{}",name)
             }
         }
     }
@@ -32,17 +54,95 @@ pub mod model{
         pub fn new_f64(val:f64) -> Value{
             Value::Value(val)
         }
+
+        pub fn new_var(name:&str) -> Value{
+            Value::Variable(name.to_string())
+        }
+
+        pub fn new_func(func:Function) -> Value{
+            Value::Function(func)
+        }
     }
     
-    #[derive(Clone)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
     pub enum Operator{
         Plus,
         Minus,
         Mult,
-        Div
+        Div,
+        Pow,
+        // Bitwise, on integers only.
+        And,
+        Or,
+        Xor,
+        Shl,
+        Shr
+    }
+
+    // Applies a bitwise operator to two f64 holding integers, NaN otherwise.
+    fn integer_op(op: &Operator, left: f64, right: f64) -> f64{
+        let whole = |x: f64| x.fract() == 0f64 && x.abs() < 9.2e18;
+        if !whole(left) || !whole(right){
+            return f64::NAN;
+        }
+        let (a, b) = (left as i64, right as i64);
+        let shift = u32::try_from(b).ok().filter(|n| *n < 64);
+        match op{
+            Operator::And => (a & b) as f64,
+            Operator::Or => (a | b) as f64,
+            Operator::Xor => (a ^ b) as f64,
+            Operator::Shl => shift.and_then(|n| a.checked_mul(1i64.checked_shl(n)?)).map_or(f64::NAN, |x| x as f64),
+            Operator::Shr => shift.map_or(f64::NAN, |n| (a >> n) as f64),
+            _ => f64::NAN
+        }
     }
 
     impl Operator{
+        pub fn from_symbol(symbol:&str) -> Option<Operator>{
+            match symbol{
+                "+" => Some(Operator::Plus),
+                "-" => Some(Operator::Minus),
+                "*" => Some(Operator::Mult),
+                "/" => Some(Operator::Div),
+                "^" => Some(Operator::Pow),
+                "&" => Some(Operator::And),
+                "|" => Some(Operator::Or),
+                "xor" => Some(Operator::Xor),
+                "<<" => Some(Operator::Shl),
+                ">>" => Some(Operator::Shr),
+                _ => None
+            }
+        }
+
+        // Bitwise operators bind looser than arithmetic, in the order C uses.
+        pub fn precedence(&self) -> u8{
+            match self{
+                Operator::Or => 1,
+                Operator::Xor => 2,
+                Operator::And => 3,
+                Operator::Shl | Operator::Shr => 4,
+                Operator::Plus | Operator::Minus => 5,
+                Operator::Mult | Operator::Div => 6,
+                Operator::Pow => 8
+            }
+        }
+
+        pub fn right_assoc(&self) -> bool{
+            matches!(self, Operator::Pow)
+        }
+
+        pub fn apply<N: Number>(&self, left:&N, right:&N) -> Result<N, EvalError>{
+            match self{
+                Operator::Plus => left.add(right),
+                Operator::Minus => left.sub(right),
+                Operator::Mult => left.mul(right),
+                Operator::Div => left.div(right),
+                Operator::Pow => left.pow(right),
+                _ => left.bitwise(*self, right)
+            }
+        }
+
+        // The f64 case of apply, also run directly by the bytecode and by constant folding.
         pub fn evaluate(&self, left:f64, right:f64) -> f64{
             match self{
                 Operator::Plus => {
@@ -56,7 +156,11 @@ pub mod model{
                 },
                 Operator::Div => {
                     left/right
-                }
+                },
+                Operator::Pow => {
+                    left.powf(right)
+                },
+                _ => integer_op(self, left, right)
             }
         }
     }
@@ -67,7 +171,159 @@ pub mod model{
                 Operator::Plus => write!(f,"+"),
                 Operator::Minus => write!(f,"-"),
                 Operator::Mult => write!(f,"*"),
-                Operator::Div => write!(f,"/")
+                Operator::Div => write!(f,"/"),
+                Operator::Pow => write!(f,"^"),
+                Operator::And => write!(f,"&"),
+                Operator::Or => write!(f,"|"),
+                Operator::Xor => write!(f,"xor"),
+                Operator::Shl => write!(f,"<<"),
+                Operator::Shr => write!(f,">>")
+            }
+        }
+    }
+
+    // Functions take their single argument from the left child.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Function{
+        Sin,
+        Cos,
+        Tan,
+        Exp,
+        Ln,
+        Sqrt,
+        Abs,
+        Neg,
+        // Written n! in infix, only defined for non-negative integers.
+        Fact,
+        // Takes n in the left child and k in the right child.
+        Binom,
+        // Parts of a complex number, trivial on the reals.
+        Re,
+        Im,
+        Conj,
+        Arg,
+        // Bit twiddling, written ~x for Not. Rotations take the count in the right child.
+        Not,
+        Popcount,
+        Rotl,
+        Rotr
+    }
+
+    // Product of 1..=n, NaN unless n is a non-negative integer.
+    fn factorial(n: f64) -> f64{
+        if n < 0f64 || n.fract() != 0f64{
+            return f64::NAN;
+        }
+        let mut out = 1f64;
+        let mut i = 2f64;
+        while i <= n && out.is_finite(){
+            out *= i;
+            i += 1f64;
+        }
+        out
+    }
+
+    fn binomial(n: f64, k: f64) -> f64{
+        if n < 0f64 || k < 0f64 || n.fract() != 0f64 || k.fract() != 0f64{
+            return f64::NAN;
+        }
+        if k > n{
+            return 0f64;
+        }
+        let k = k.min(n - k);
+        let mut out = 1f64;
+        let mut i = 1f64;
+        while i <= k{
+            out = out * (n - k + i) / i;
+            i += 1f64;
+        }
+        out.round()
+    }
+
+    impl Function{
+        pub fn from_name(name:&str) -> Option<Function>{
+            match name{
+                "sin" => Some(Function::Sin),
+                "cos" => Some(Function::Cos),
+                "tan" => Some(Function::Tan),
+                "exp" => Some(Function::Exp),
+                "ln" => Some(Function::Ln),
+                "sqrt" => Some(Function::Sqrt),
+                "abs" => Some(Function::Abs),
+                "neg" => Some(Function::Neg),
+                "fact" => Some(Function::Fact),
+                "binom" => Some(Function::Binom),
+                "re" => Some(Function::Re),
+                "im" => Some(Function::Im),
+                "conj" => Some(Function::Conj),
+                "arg" => Some(Function::Arg),
+                "not" => Some(Function::Not),
+                "popcount" => Some(Function::Popcount),
+                "rotl" => Some(Function::Rotl),
+                "rotr" => Some(Function::Rotr),
+                _ => None
+            }
+        }
+
+        pub fn arity(&self) -> usize{
+            match self{
+                Function::Binom | Function::Rotl | Function::Rotr => 2,
+                _ => 1
+            }
+        }
+
+        pub fn evaluate(&self, arg:f64) -> f64{
+            match self{
+                Function::Sin => arg.sin(),
+                Function::Cos => arg.cos(),
+                Function::Tan => arg.tan(),
+                Function::Exp => arg.exp(),
+                Function::Ln => arg.ln(),
+                Function::Sqrt => arg.sqrt(),
+                Function::Abs => arg.abs(),
+                Function::Neg => -arg,
+                Function::Fact => factorial(arg),
+                Function::Binom | Function::Rotl | Function::Rotr => f64::NAN,
+                Function::Not => integer_op(&Operator::Xor, arg, -1f64),
+                Function::Popcount => if arg.fract() == 0f64 && arg.abs() < 9.2e18 {(arg as i64).count_ones() as f64} else {f64::NAN},
+                Function::Re | Function::Conj => arg,
+                Function::Im => if arg.is_nan() {arg} else {0f64},
+                Function::Arg => if arg < 0f64 {std::f64::consts::PI} else if arg.is_nan() {arg} else {0f64}
+            }
+        }
+
+        pub fn evaluate_binary(&self, left:f64, right:f64) -> f64{
+            let count = |n: f64| if n.fract() == 0f64 && n.abs() < 9.2e18 {Some((n as i64).rem_euclid(64) as u32)} else {None};
+            match (self, count(left), count(right)){
+                (Function::Binom, _, _) => binomial(left, right),
+                (Function::Rotl, Some(_), Some(n)) => (left as i64).rotate_left(n) as f64,
+                (Function::Rotr, Some(_), Some(n)) => (left as i64).rotate_right(n) as f64,
+                _ => f64::NAN
+            }
+        }
+    }
+
+    impl fmt::Display for Function{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            match self{
+                Function::Sin => write!(f,"sin"),
+                Function::Cos => write!(f,"cos"),
+                Function::Tan => write!(f,"tan"),
+                Function::Exp => write!(f,"exp"),
+                Function::Ln => write!(f,"ln"),
+                Function::Sqrt => write!(f,"sqrt"),
+                Function::Abs => write!(f,"abs"),
+                Function::Neg => write!(f,"neg"),
+                Function::Fact => write!(f,"fact"),
+                Function::Binom => write!(f,"binom"),
+                Function::Re => write!(f,"re"),
+                Function::Im => write!(f,"im"),
+                Function::Conj => write!(f,"conj"),
+                Function::Arg => write!(f,"arg"),
+                Function::Not => write!(f,"not"),
+                Function::Popcount => write!(f,"popcount"),
+                Function::Rotl => write!(f,"rotl"),
+                Function::Rotr => write!(f,"rotr")
             }
         }
     }
@@ -90,16 +346,39 @@ pub mod model{
         OpOnNone
     }
 
+    #[derive(Debug)]
     pub enum EvalError{
         BadTree,
-        UnexpectedOp
+        UnexpectedOp,
+        UnknownVariable(String),
+        BadRange,
+        DivisionByZero,
+        NotExact,
+        Overflow,
+        // Adding metres to seconds and the like.
+        DimensionMismatch,
+        // A currency code the rate table has no rate for.
+        MissingRate(String),
+        // Adding two dates and the like.
+        TypeMismatch,
+        // An integral that ran out of intervals, with the error estimate it got to.
+        NotConverged(f64)
     }
 
     impl fmt::Display for EvalError{
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
             match self{
                 EvalError::BadTree => write!(f, "BadTree"),
-                EvalError::UnexpectedOp => write!(f, "UnexpectedOp")
+                EvalError::UnexpectedOp => write!(f, "UnexpectedOp"),
+                EvalError::UnknownVariable(name) => write!(f, "UnknownVariable {}", name),
+                EvalError::BadRange => write!(f, "BadRange"),
+                EvalError::DivisionByZero => write!(f, "DivisionByZero"),
+                EvalError::NotExact => write!(f, "NotExact"),
+                EvalError::Overflow => write!(f, "Overflow"),
+                EvalError::DimensionMismatch => write!(f, "DimensionMismatch"),
+                EvalError::MissingRate(code) => write!(f, "MissingRate {}", code),
+                EvalError::TypeMismatch => write!(f, "TypeMismatch"),
+                EvalError::NotConverged(error) => write!(f, "NotConverged, error estimate {:e}", error)
             }
         }
     }
@@ -113,27 +392,99 @@ pub mod model{
         }
     }
 
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Aggregate{
+        Integrate,
+        Sum,
+        Product
+    }
+
+    impl Aggregate{
+        pub fn from_name(name:&str) -> Option<Aggregate>{
+            match name{
+                "integrate" => Some(Aggregate::Integrate),
+                "sum" => Some(Aggregate::Sum),
+                "product" => Some(Aggregate::Product),
+                _ => None
+            }
+        }
+
+        // Integrals come with their error estimate, sums and products are exact up to rounding.
+        pub fn evaluate<F: FnMut(f64) -> Result<f64, EvalError>>(&self, from:f64, to:f64, body:F) -> Result<Quadrature, EvalError>{
+            match self{
+                Aggregate::Integrate => calculus::integrate(body, from, to),
+                Aggregate::Sum => Ok(Quadrature::exact(calculus::sum(body, from, to)?)),
+                Aggregate::Product => Ok(Quadrature::exact(calculus::product(body, from, to)?))
+            }
+        }
+    }
+
+    impl fmt::Display for Aggregate{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            match self{
+                Aggregate::Integrate => write!(f,"integrate"),
+                Aggregate::Sum => write!(f,"sum"),
+                Aggregate::Product => write!(f,"product")
+            }
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    pub enum Layout{
+        // Root on the left, right subtree above the left one.
+        Rotated,
+        // Root on top with ├── and └── branches, left child first.
+        Boxed
+    }
+
     impl<T:fmt::Display> Tree<T>{
         pub fn display_tree(&mut self){
-            match self.head{
-                Some(n) => {
-                    unsafe{
-                        (*n.as_ptr()).display_rec(0);
-                    }
+            let _ = self.render_io(&mut io::stdout(), Layout::Rotated);
+        }
+
+        pub fn render<W: fmt::Write>(&self, out: &mut W, layout: Layout) -> fmt::Result{
+            match (self.root_node(), layout){
+                (Some(n), Layout::Rotated) => n.display_rec(out, 0),
+                (Some(n), Layout::Boxed) => {
+                    n.write_value(out)?;
+                    n.display_boxed(out, "")
                 },
-                None => ()
+                (None, _) => Ok(())
             }
         }
+
+        pub fn render_io<W: io::Write>(&self, out: &mut W, layout: Layout) -> io::Result<()>{
+            let mut buf = String::new();
+            if self.render(&mut buf, layout).is_err(){
+                return Err(io::Error::other("formatting failed"));
+            }
+            out.write_all(buf.as_bytes())
+        }
     }
 
     impl<T> Tree<T>{
-        fn new() -> Self{
+        pub fn new() -> Self{
             Tree{
                 head: None,
                 current: None
             }
         }
 
+        pub fn from_root(root: Box<Node<T>>) -> Self{
+            let ptr: Option<NonNull<Node<T>>> = Some(Box::leak(root).into());
+            Tree{
+                head: ptr,
+                current: ptr
+            }
+        }
+
+        pub fn root_node(&self) -> Option<&Node<T>>{
+            unsafe{
+                self.head.map(|n| &*n.as_ptr())
+            }
+        }
+
+        #[allow(clippy::single_match)]
         fn push(&mut self, new_head: T, dir:Dir) -> Result<(),TreeError>{
             match self.current{
                 Some(old) =>{
@@ -177,7 +528,8 @@ pub mod model{
             self.push(new_head, Dir::Right)
         }
 
-        fn set_current(&mut self, n: T){
+        #[allow(clippy::partialeq_to_none)]
+        pub fn set_current(&mut self, n: T){
             match self.current{
                 Some(node) => {
                     unsafe{
@@ -330,10 +682,12 @@ pub mod model{
         }
     }
 
-    impl Tree<Value>{
-        pub fn evaluate_it3(&mut self) -> Result<f64, EvalError>{
+    // The tree walkers as first written, kept for the benchmark. They know operators only.
+    #[allow(clippy::type_complexity)]
+    impl<N: Number> Tree<Value<N>>{
+        pub fn evaluate_it3(&mut self) -> Result<N, EvalError>{
             self.select_root();
-            let mut stack: Vec<(NonNull<Node<Value>>, Option<f64>, Option<f64>)> = Vec::new();
+            let mut stack: Vec<(NonNull<Node<Value<N>>>, Option<N>, Option<N>)> = Vec::new();
             let mut depth = 0;
 
             loop{
@@ -374,7 +728,7 @@ pub mod model{
                                     continue;
                                 },
                                 Some((_,Some(left), Some(right))) => {
-                                    let eval_val = op.evaluate(left.clone(), right.clone());
+                                    let eval_val = op.apply(left, right)?;
                                     if stack.len() == 1{
                                         return Ok(eval_val);
                                     }
@@ -400,7 +754,8 @@ pub mod model{
                                     return Err(EvalError::BadTree);
                                 }
                             }
-                        }
+                        },
+                        _ => return Err(EvalError::UnexpectedOp)
                     }
                 }else{
                     return Err(EvalError::BadTree);
@@ -408,90 +763,54 @@ pub mod model{
             }
         }
 
-        pub fn evaluate_it2(&mut self) -> Result<f64, EvalError>{
-            let mut ptr_stack: Vec<NonNull<Node<Value>>> = Vec::new();
-            let mut buf_len = 0;
-            let mut val_buffer = [0f64;2];
+        // Post-order walk: an operator is visited once on the way down and
+        // applied on the way back up to the two values its children left.
+        pub fn evaluate_it2(&mut self) -> Result<N, EvalError>{
+            let mut ptr_stack: Vec<(NonNull<Node<Value<N>>>, bool)> = Vec::new();
+            let mut val_buffer: Vec<N> = Vec::new();
             self.select_root();
-            loop{             
-                if let Ok(Some(val_ref)) = self.get_current(){
-                    let val = val_ref.clone();
-                    match val{
-                        Value::Value(num) => return Ok(num),
-                        Value::Operator(op) => {
-                            if let (Ok(Some(left_val_ref)), Ok(Some(right_val_ref)))
-                                     = (self.get_left(), self.get_right()){
-                                let left_val = left_val_ref.clone();
-                                let right_val = right_val_ref.clone();
-                                match (left_val, right_val){
-                                    (Value::Value(left_f64), Value::Value(right_f64)) => {
-                                        val_buffer[buf_len] = op.evaluate(left_f64, right_f64);
-                                        buf_len += 1;
-                                        match ptr_stack.pop(){
-                                            Some(prev) => self.current = Some(prev),
-                                            None => break
-                                        };
-                                    },
-                                    (Value::Operator(_),Value::Value(right_f64)) => {
-                                        if buf_len > 0 {
-                                            val_buffer[buf_len - 1] = op.evaluate(val_buffer[buf_len - 1], right_f64);
-                                            self.current = ptr_stack.pop();
-                                        }else{
-                                            ptr_stack.push(self.current.unwrap());
-                                            match self.go_left(){Ok(_) => (), Err(_) => return Err(EvalError::BadTree)};
-                                        }
-                                    },
-                                    (Value::Value(left_f64),Value::Operator(_)) => {
-                                        
-                                        if buf_len > 0 {
-                                            val_buffer[buf_len - 1] = op.evaluate(left_f64, val_buffer[buf_len - 1]);
-                                            match ptr_stack.pop(){
-                                                Some(prev) => self.current = Some(prev),
-                                                None => break
-                                            };
-                                        }else{
-                                            ptr_stack.push(self.current.unwrap());
-                                            match self.go_right(){Ok(_) => (), Err(_) => return Err(EvalError::BadTree)};
-                                        }
-                                    }
-                                    (Value::Operator(_),Value::Operator(_)) => {
-                                        if buf_len > 1 {
-                                            let dir: usize;
-                                            if self.current.eq(unsafe{&(*self.current.unwrap().as_ptr()).left}){
-                                                dir = 0;
-                                            }else{
-                                                dir = 1;
-                                            }
-                                            val_buffer[dir] = op.evaluate(val_buffer[0], val_buffer[1]);
-                                            match ptr_stack.pop(){
-                                                Some(prev) => self.current = Some(prev),
-                                                None => break
-                                            };
-                                        }else if buf_len > 0{
-                                            ptr_stack.push(self.current.unwrap());
-                                            match self.go_right(){Ok(_) => (), Err(_) => return Err(EvalError::BadTree)};
-                                        }else{
-                                            ptr_stack.push(self.current.unwrap());
-                                            match self.go_left(){Ok(_) => (), Err(_) => return Err(EvalError::BadTree)};
-                                        }
-                                    }
-                                }
-                            }else{
-                                return Err(EvalError::BadTree);
+            match self.current{
+                Some(root) => ptr_stack.push((root, false)),
+                None => return Err(EvalError::BadTree)
+            }
+            while let Some((node, visited)) = ptr_stack.pop(){
+                self.current = Some(node);
+                let val = match self.get_current(){
+                    Ok(Some(val_ref)) => val_ref.clone(),
+                    _ => return Err(EvalError::BadTree)
+                };
+                match val{
+                    Value::Value(num) => val_buffer.push(num),
+                    Value::Operator(op) => {
+                        if visited{
+                            let right = val_buffer.pop().ok_or(EvalError::BadTree)?;
+                            let left = val_buffer.pop().ok_or(EvalError::BadTree)?;
+                            val_buffer.push(op.apply(&left, &right)?);
+                        }else{
+                            let (left, right) = unsafe{((*node.as_ptr()).left, (*node.as_ptr()).right)};
+                            match (left, right){
+                                (Some(left), Some(right)) => {
+                                    ptr_stack.push((node, true));
+                                    ptr_stack.push((right, false));
+                                    ptr_stack.push((left, false));
+                                },
+                                _ => return Err(EvalError::BadTree)
                             }
                         }
-                    }
-                }else{
-                    return Err(EvalError::BadTree);
+                    },
+                    _ => return Err(EvalError::UnexpectedOp)
                 }
             }
-            Ok(val_buffer[0])
+            match (val_buffer.pop(), val_buffer.is_empty()){
+                (Some(num), true) => Ok(num),
+                _ => Err(EvalError::BadTree)
+            }
         }
 
-        pub fn evaluate_it1(&mut self) -> Result<f64, EvalError>{
+        pub fn evaluate_it1(&mut self) -> Result<N, EvalError>{
             self.select_root();
-            let mut val_stack: Vec<Value> = Vec::new();
-            let mut ptr_stack: Vec<NonNull<Node<Value>>> = Vec::new();
+            let mut val_stack: Vec<Value<N>> = Vec::new();
+            let mut ptr_stack: Vec<NonNull<Node<Value<N>>>> = Vec::new();
 
             loop{
                 if let Ok(Some(v)) = self.get_current(){
@@ -524,7 +843,7 @@ pub mod model{
                                                 val_stack.remove(val_len - 1);
                                                 val_stack.remove(val_len - 2);
                                                 val_stack.remove(val_len - 3);
-                                                val_stack.push(Value::Value(op.evaluate(left.clone(), right.clone())));
+                                                val_stack.push(Value::Value(op.apply(&left, &right)?));
                                             }else{
                                                 break;
                                             }
@@ -560,7 +879,7 @@ pub mod model{
                             val_stack.remove(val_len - 1);
                             val_stack.remove(val_len - 2);
                             val_stack.remove(val_len - 3);
-                            val_stack.push(Value::Value(op.evaluate(left.clone(), right.clone())));
+                            val_stack.push(Value::Value(op.apply(&left, &right)?));
                         }else{
                             break;
                         }
@@ -570,13 +889,19 @@ pub mod model{
                 }
             }
         }
+    }
 
+    impl Tree<Value>{
         pub fn rec_evaluate(&mut self) -> Result<f64, EvalError>{
+            self.rec_evaluate_env(&Env::new())
+        }
+
+        pub fn rec_evaluate_env(&mut self, env: &Env) -> Result<f64, EvalError>{
             
             match self.head{
                 Some(node) => {
                     unsafe{
-                        (*node.as_ptr()).rec_evaluate()
+                        (*node.as_ptr()).evaluate_number(env)
                     }
                 },
                 None => Err(EvalError::BadTree)
@@ -592,44 +917,116 @@ pub mod model{
         right: Option<NonNull<Node<T>>>
     }
 
-    impl Node<Value>{
-        fn rec_evaluate(&self) -> Result<f64, EvalError>{
+    impl<N: Number> Node<Value<N>>{
+        // A missing operand counts as 0.
+        fn evaluate_operand(node: Option<&Self>, env: &HashMap<String, N>) -> Result<N, EvalError>{
+            match node{
+                Some(n) => n.evaluate_number(env),
+                None => Ok(N::zero())
+            }
+        }
+
+        pub fn evaluate_number(&self, env: &HashMap<String, N>) -> Result<N, EvalError>{
             match &self.value{
                 Some(val) => {
                     match val{
                         Value::Value(num) => Ok(num.clone()),
                         Value::Operator(op) => {
-                            unsafe{
-                                let left = match self.left{
-                                    Some(node) => {
-                                        match (*node.as_ptr()).rec_evaluate(){
-                                            Ok(s) => s,
-                                            Err(e) => return Err(e)
-                                        }
-                                    },
-                                    None => 0f64,
-                                };
-
-                                let right = match self.right{
-                                    Some(node) => {
-                                        match (*node.as_ptr()).rec_evaluate(){
-                                            Ok(s) => s,
-                                            Err(e) => return Err(e)
-                                        }
-                                    },
-                                    None => 0f64,
-                                };
-
-                                Ok(op.evaluate(left, right))
+                            let left = Node::evaluate_operand(self.left_node(), env)?;
+                            let right = Node::evaluate_operand(self.right_node(), env)?;
+                            op.apply(&left, &right)
+                        },
+                        Value::Variable(name) => {
+                            match env.get(name){
+                                Some(num) => Ok(num.clone()),
+                                None => Err(EvalError::UnknownVariable(name.clone()))
                             }
-                        }
+                        },
+                        Value::Function(func) if func.arity() == 2 => {
+                            match (self.left_node(), self.right_node()){
+                                (Some(left), Some(right)) => left.evaluate_number(env)?.call_binary(*func, &right.evaluate_number(env)?),
+                                _ => Err(EvalError::BadTree)
+                            }
+                        },
+                        Value::Function(func) => {
+                            match self.left_node(){
+                                Some(arg) => arg.evaluate_number(env)?.call(*func),
+                                None => Err(EvalError::BadTree)
+                            }
+                        },
+                        Value::Aggregate(agg) => {
+                            let (body, range) = match (self.left_node(), self.right_node()){
+                                (Some(body), Some(range)) => (body, range),
+                                _ => return Err(EvalError::BadTree)
+                            };
+                            match (&range.value, range.left_node(), range.right_node()){
+                                (Some(Value::Range(var)), Some(from), Some(to)) => {
+                                    let from = from.evaluate_number(env)?;
+                                    let to = to.evaluate_number(env)?;
+                                    let mut inner = env.clone();
+                                    N::aggregate(*agg, &from, &to, |x| {
+                                        inner.insert(var.clone(), x.clone());
+                                        body.evaluate_number(&inner)
+                                    })
+                                },
+                                _ => Err(EvalError::BadTree)
+                            }
+                        },
+                        Value::Range(_) => Err(EvalError::BadTree)
                     }
                 },
-                None => return Err(EvalError::BadTree)
+                None => Err(EvalError::BadTree)
             }
         }
     }
 
+    impl<N: Number> Tree<Value<N>>{
+        pub fn evaluate_number(&self, env: &HashMap<String, N>) -> Result<N, EvalError>{
+            match self.root_node(){
+                Some(root) => root.evaluate_number(env),
+                None => Err(EvalError::BadTree)
+            }
+        }
+    }
+
+    impl<N> Value<N>{
+        // Same value with the number, if any, converted by f.
+        pub fn try_map<M, E, F: Fn(&N) -> Result<M, E>>(&self, f: &F) -> Result<Value<M>, E>{
+            Ok(match self{
+                Value::Value(num) => Value::Value(f(num)?),
+                Value::Operator(op) => Value::Operator(*op),
+                Value::Variable(name) => Value::Variable(name.clone()),
+                Value::Function(func) => Value::Function(*func),
+                Value::Aggregate(agg) => Value::Aggregate(*agg),
+                Value::Range(name) => Value::Range(name.clone())
+            })
+        }
+    }
+
+    impl<N> Node<Value<N>>{
+        pub fn try_map<M, E, F: Fn(&N) -> Result<M, E>>(&self, f: &F) -> Result<Box<Node<Value<M>>>, E>{
+            let left = match self.left_node(){
+                Some(n) => Some(n.try_map(f)?),
+                None => None
+            };
+            let right = match self.right_node(){
+                Some(n) => Some(n.try_map(f)?),
+                None => None
+            };
+            let mut node = Box::new(Node{
+                up: None,
+                left: None,
+                value: match &self.value{
+                    Some(val) => Some(val.try_map(f)?),
+                    None => None
+                },
+                right: None
+            });
+            node.set_left(left);
+            node.set_right(right);
+            Ok(node)
+        }
+    }
 
     impl<T> Node<T>{
         fn new(val: T) -> Self{
@@ -641,6 +1038,25 @@ pub mod model{
             }
         }
 
+        pub fn with_children(val: T, left: Option<Box<Self>>, right: Option<Box<Self>>) -> Box<Self>{
+            let mut node = Box::new(Node::new(val));
+            node.set_left(left);
+            node.set_right(right);
+            node
+        }
+
+        pub fn left_node(&self) -> Option<&Self>{
+            unsafe{
+                self.left.map(|n| &*n.as_ptr())
+            }
+        }
+
+        pub fn right_node(&self) -> Option<&Self>{
+            unsafe{
+                self.right.map(|n| &*n.as_ptr())
+            }
+        }
+
         pub fn get_left(&self) -> Option<NonNull<Self>>{
             match self.left{
                 Some(n) => {
@@ -695,122 +1111,606 @@ pub mod model{
         }
     }
 
+    impl<T: Clone> Node<T>{
+        pub fn deep_copy(&self) -> Box<Self>{
+            let left = self.left_node().map(|n| n.deep_copy());
+            let right = self.right_node().map(|n| n.deep_copy());
+            let mut node = Box::new(Node{
+                up: None,
+                left: None,
+                value: self.value.clone(),
+                right: None
+            });
+            node.set_left(left);
+            node.set_right(right);
+            node
+        }
+    }
+
     impl<T:fmt::Display> Node<T>{
-        pub fn display_rec(&self, depth: u32){
-            match self.right{
-                Some(n) => {
-                    unsafe{
-                        (*n.as_ptr()).display_rec(depth + 1);
-                    }
-                },
-                None => ()
+        pub fn display_rec<W: fmt::Write>(&self, out: &mut W, depth: u32) -> fmt::Result{
+            if let Some(n) = self.right_node(){
+                n.display_rec(out, depth + 1)?;
             }
 
             for _ in 0..(depth){   
-                print!("   ");
+                write!(out, "   ")?;
+            }
+            self.write_value(out)?;
+
+            if let Some(n) = self.left_node(){
+                n.display_rec(out, depth + 1)?;
+            }
+            Ok(())
+        }
+
+        fn display_boxed<W: fmt::Write>(&self, out: &mut W, prefix: &str) -> fmt::Result{
+            let children: Vec<&Node<T>> = [self.left_node(), self.right_node()].into_iter().flatten().collect();
+            for (i, child) in children.iter().enumerate(){
+                let last = i + 1 == children.len();
+                write!(out, "{}{}", prefix, if last {"└── "} else {"├── "})?;
+                child.write_value(out)?;
+                child.display_boxed(out, &format!("{}{}", prefix, if last {"    "} else {"│   "}))?;
             }
+            Ok(())
+        }
+
+        fn write_value<W: fmt::Write>(&self, out: &mut W) -> fmt::Result{
             match &self.value{
-                Some(valu) => println!("{}", valu),
-                None => println!("None\n")
-               
+                Some(valu) => writeln!(out, "{}", valu),
+                None => writeln!(out, "None\n")
             }
+        }
+    }
+    
 
-            match self.left{
-                Some(n) => {
-                    unsafe{
-                        (*n.as_ptr()).display_rec(depth + 1);
+    enum Token{
+        Number(String),
+        Ident(String),
+        Operator(Operator),
+        LParen,
+        RParen,
+        Comma,
+        Bang,
+        Tilde
+    }
+
+    enum Pending{
+        Operator(Operator),
+        // Unary minus or ~, waiting for its operand.
+        Prefix(Function),
+        // Both count the commas seen so far in their argument list.
+        Function(Function, usize),
+        Aggregate(Aggregate, usize),
+        LParen
+    }
+
+    pub fn parse_number(src: &str) -> Result<f64, &'static str>{
+        if src.is_empty() || src.eq(".") || src.matches('.').count() > 1{
+            return Err("Bad float");
+        }
+        match src.parse::<f64>(){
+            Ok(num) => Ok(num),
+            Err(_) => Err("Bad float")
+        }
+    }
+
+    // Exponents past this would only spell out overflow or underflow.
+    const MAX_EXPONENT: i64 = 1000;
+
+    // Digits of the radix with _ allowed between them, the separators are dropped.
+    fn take_digits(chars: &mut Peekable<Chars>, radix: u32, point: bool) -> String{
+        let mut out = String::new();
+        loop{
+            match chars.peek(){
+                Some(&d) if d.is_digit(radix) || point && d == '.' => out.push(d),
+                Some('_') if out.ends_with(|c: char| c.is_digit(radix)) => {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    if !ahead.peek().is_some_and(|d| d.is_digit(radix)){
+                        break;
                     }
                 },
-                None => ()
+                _ => break
             }
+            chars.next();
         }
+        out
     }
-    
 
-    pub fn parse_to_tree<'a>(src: &str) -> Result<Node<Value>, &str>{
+    // Moves the decimal point of digits exp places to the right.
+    fn shift_point(digits: &str, exp: i64) -> String{
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        let all = format!("{}{}", int, frac);
+        let point = int.len() as i64 + exp;
+        let out = if point <= 0{
+            format!("0.{}{}", "0".repeat(point.unsigned_abs() as usize), all)
+        }else if point as usize >= all.len(){
+            format!("{}{}", all, "0".repeat(point as usize - all.len()))
+        }else{
+            format!("{}.{}", &all[..point as usize], &all[point as usize..])
+        };
+        let trimmed = out.trim_start_matches('0');
+        if trimmed.is_empty() || trimmed.starts_with('.') {format!("0{}", trimmed)} else {trimmed.to_string()}
+    }
 
-        let mut temp = String::new();
-        
+    // A decimal literal, 1e-9 is written out as 0.000000001 so that every leaf type reads it exactly.
+    // A letter after the e is a name, as in 3eV, anything else after it has to be the exponent.
+    fn take_decimal(chars: &mut Peekable<Chars>) -> Result<String, &'static str>{
+        let digits = take_digits(chars, 10, true);
+        let mut ahead = chars.clone();
+        match ahead.next(){
+            Some('_') => return Err("Bad literal"),
+            Some('e' | 'E') => (),
+            _ => return Ok(digits)
+        }
+        let negative = ahead.next_if_eq(&'-').is_some();
+        let signed = negative || ahead.next_if_eq(&'+').is_some();
+        let exp = take_digits(&mut ahead, 10, false);
+        if exp.is_empty(){
+            return match ahead.peek(){
+                Some(c) if !signed && (c.is_ascii_alphabetic() || *c == '_') => Ok(digits),
+                _ => Err("Bad literal")
+            };
+        }
+        *chars = ahead;
+        if digits.matches('.').count() > 1 || !digits.contains(|c: char| c.is_ascii_digit()){
+            return Err("Bad float");
+        }
+        let exp: i64 = exp.parse().ok().filter(|e| *e <= MAX_EXPONENT).ok_or("Exponent too large")?;
+        Ok(shift_point(&digits, if negative {-exp} else {exp}))
+    }
 
+    // 0xFF, 0o755 and 0b1010 after the prefix, given to the leaf in decimal. So is 0xFF.8, a power of two always divides out.
+    fn take_radix(chars: &mut Peekable<Chars>, radix: u32) -> Result<String, &'static str>{
+        let digits = take_digits(chars, radix, true);
+        match chars.peek(){
+            Some('_') => return Err("Bad literal"),
+            Some(c) if c.is_ascii_alphanumeric() => return Err("Bad digit"),
+            _ => ()
+        }
+        let (int, frac) = digits.split_once('.').unwrap_or((&digits, ""));
+        if frac.contains('.'){
+            return Err("Bad literal");
+        }
+        let int = u128::from_str_radix(int, radix).map_err(|_| "Literal out of range")?;
+        if frac.is_empty(){
+            return Ok(int.to_string());
+        }
+        Ok(format!("{}.{}", int, radix_fraction(frac, radix)))
+    }
+
+    // Decimal digits of the fraction, each one carried out of multiplying the radix digits by ten.
+    fn radix_fraction(frac: &str, radix: u32) -> String{
+        let mut digits: Vec<u32> = frac.chars().filter_map(|d| d.to_digit(radix)).collect();
+        let mut out = String::new();
+        while digits.iter().any(|d| *d != 0){
+            let mut carry = 0;
+            for d in digits.iter_mut().rev(){
+                let product = *d * 10 + carry;
+                *d = product % radix;
+                carry = product / radix;
+            }
+            out.push_str(&carry.to_string());
+        }
+        if out.is_empty() {"0".to_string()} else {out}
+    }
 
-        for (_i,c) in src.char_indices(){
-            // println!("{} {}",i, c);
+    // Takes chars matching shape, where d stands for any digit, or leaves them all in place.
+    fn take_shape(chars: &mut Peekable<Chars>, shape: &str) -> Option<String>{
+        let mut ahead = chars.clone();
+        let mut out = String::new();
+        for s in shape.chars(){
+            let c = ahead.next()?;
+            if (s == 'd' && c.is_ascii_digit()) || (s != 'd' && c == s){
+                out.push(c);
+            }else{
+                return None;
+            }
+        }
+        if ahead.peek().is_some_and(|c| c.is_ascii_digit()){
+            return None;
+        }
+        *chars = ahead;
+        Some(out)
+    }
+
+    // Numbers glued to words, at least two in a row like 3h 20min, after the first number has been read.
+    fn take_compound(chars: &mut Peekable<Chars>, first: &str) -> Option<String>{
+        let letters = |it: &mut Peekable<Chars>| {
+            let mut word = String::new();
+            while let Some(c) = it.next_if(|c| c.is_ascii_alphabetic()){
+                word.push(c);
+            }
+            word
+        };
+        let mut ahead = chars.clone();
+        let word = letters(&mut ahead);
+        if word.is_empty(){
+            return None;
+        }
+        let mut out = format!("{}{}", first, word);
+        let mut parts = 1;
+        loop{
+            let mut next = ahead.clone();
+            while next.next_if_eq(&' ').is_some(){}
+            let mut digits = String::new();
+            while let Some(d) = next.next_if(|d| d.is_ascii_digit() || *d == '.'){
+                digits.push(d);
+            }
+            let word = letters(&mut next);
+            if digits.is_empty() || word.is_empty(){
+                break;
+            }
+            out.push_str(&format!(" {}{}", digits, word));
+            parts += 1;
+            ahead = next;
+        }
+        if parts < 2 || ahead.peek().is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_'){
+            return None;
+        }
+        *chars = ahead;
+        Some(out)
+    }
+
+    // Literal shapes that only some modes read, all off by default.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct Literals{
+        // x +- dx spelled in ASCII, otherwise 1+-2 is 1 + (-2).
+        pub plus_minus: bool,
+        // Dates like 2026-10-18T09:30 and durations like 3h 20min.
+        pub dates: bool
+    }
+
+    fn tokenize(src: &str, literals: Literals) -> Result<Vec<Token>, &'static str>{
+        let mut tokens = Vec::new();
+        let mut chars = src.chars().peekable();
+
+        while let Some(&c) = chars.peek(){
             match c{
-                '0' => {
-                    temp.push_str("0");
+                '0'..='9' | '.' => {
+                    let mut ahead = chars.clone();
+                    let radix = match (ahead.next(), ahead.next()){
+                        (Some('0'), Some('x' | 'X')) => 16,
+                        (Some('0'), Some('o' | 'O')) => 8,
+                        (Some('0'), Some('b' | 'B')) => 2,
+                        _ => 10
+                    };
+                    if radix != 10{
+                        if !ahead.peek().is_some_and(|d| d.is_digit(radix)){
+                            return Err("Bad literal");
+                        }
+                        tokens.push(Token::Number(take_radix(&mut ahead, radix)?));
+                        chars = ahead;
+                        continue;
+                    }
+                    let mut temp = take_decimal(&mut chars)?;
+                    // Dates like 2026-10-18 or 2026-10-18T09:30 and durations like 3h 20min are single literals.
+                    if literals.dates && temp.len() == 4 && temp.bytes().all(|b| b.is_ascii_digit()){
+                        if let Some(date) = take_shape(&mut chars, "-dd-dd"){
+                            temp.push_str(&date);
+                            if let Some(time) = take_shape(&mut chars, "Tdd:dd"){
+                                temp.push_str(&time);
+                                temp.push_str(&take_shape(&mut chars, ":dd").unwrap_or_default());
+                            }
+                            tokens.push(Token::Number(temp));
+                            continue;
+                        }
+                    }
+                    if let Some(compound) = literals.dates.then(|| take_compound(&mut chars, &temp)).flatten(){
+                        tokens.push(Token::Number(compound));
+                        continue;
+                    }
+                    // An imaginary literal like 4i, left for the leaf to accept or reject.
+                    let mut ahead = chars.clone();
+                    if ahead.next() == Some('i') && !ahead.peek().is_some_and(|&d| d.is_ascii_alphanumeric() || d == '_'){
+                        temp.push('i');
+                        chars.next();
+                    }
+                    // x ± dx (or x +- dx) is one literal, the leaf decides what it means.
+                    let mut ahead = chars.clone();
+                    while ahead.next_if_eq(&' ').is_some(){}
+                    let sign = ahead.next();
+                    if sign == Some('±') || literals.plus_minus && sign == Some('+') && ahead.next_if_eq(&'-').is_some(){
+                        while ahead.next_if_eq(&' ').is_some(){}
+                        let radius = take_decimal(&mut ahead)?;
+                        if !radius.is_empty(){
+                            temp.push('±');
+                            temp.push_str(&radius);
+                            chars = ahead;
+                        }
+                    }
+                    tokens.push(Token::Number(temp));
                     continue;
                 },
-                '1' => {
-                    temp.push_str("1");
+                'a'..='z' | 'A'..='Z' | '_' => {
+                    let mut temp = String::new();
+                    while let Some(&d) = chars.peek(){
+                        if d.is_ascii_alphanumeric() || d == '_'{
+                            temp.push(d);
+                            chars.next();
+                        }else{
+                            break;
+                        }
+                    }
+                    // xor is spelled out, so it reads as an operator rather than a name.
+                    match Operator::from_symbol(&temp){
+                        Some(op) => tokens.push(Token::Operator(op)),
+                        None => tokens.push(Token::Ident(temp))
+                    }
                     continue;
                 },
-                '2' => {
-                    temp.push_str("2");
+                // [lo, hi] is one literal as well.
+                '[' => {
+                    let mut temp = String::new();
+                    for d in chars.by_ref(){
+                        temp.push(d);
+                        if d == ']'{
+                            break;
+                        }
+                    }
+                    if !temp.ends_with(']'){
+                        return Err("Missing bracket");
+                    }
+                    tokens.push(Token::Number(temp));
                     continue;
                 },
-                '3' => {
-                    temp.push_str("3");
-                    continue;
+                '(' => tokens.push(Token::LParen),
+                ')' => tokens.push(Token::RParen),
+                ',' => tokens.push(Token::Comma),
+                '!' => tokens.push(Token::Bang),
+                '~' => tokens.push(Token::Tilde),
+                '<' | '>' => {
+                    chars.next();
+                    if chars.peek() != Some(&c){
+                        return Err("Wrong input");
+                    }
+                    tokens.push(Token::Operator(if c == '<' {Operator::Shl} else {Operator::Shr}));
                 },
-                '4' => {
-                    temp.push_str("4");
-                    continue;
+                ' ' | '\t' | '\n' | '\r' => (),
+                _ => {
+                    match Operator::from_symbol(&c.to_string()){
+                        Some(op) => tokens.push(Token::Operator(op)),
+                        None => return Err("Wrong input")
+                    }
+                }
+            };
+            chars.next();
+        }
+        Ok(tokens)
+    }
+
+    // Precedence of an operator on the pending stack, unary minus and ~ sit between * and ^.
+    fn precedence(pending: &Pending) -> u8{
+        match pending{
+            Pending::Operator(op) => op.precedence(),
+            Pending::Prefix(_) => 7,
+            _ => 0
+        }
+    }
+
+    // Literals are kept as written and turned into numbers by leaf.
+    // Pops everything that binds at least as tight as op, then pushes it.
+    fn push_operator<N>(op: Operator, stack: &mut Vec<Pending>, output: &mut Vec<Value<N>>){
+        while let Some(top) = stack.last(){
+            let top_prec = precedence(top);
+            if top_prec > op.precedence() || (top_prec == op.precedence() && !op.right_assoc()){
+                match stack.pop(){
+                    Some(Pending::Operator(o)) => output.push(Value::Operator(o)),
+                    Some(Pending::Prefix(func)) => output.push(Value::Function(func)),
+                    _ => ()
+                }
+            }else{
+                break;
+            }
+        }
+        stack.push(Pending::Operator(op));
+    }
+
+    fn to_postfix<N, F: Fn(&str) -> Result<N, &'static str>>(tokens: Vec<Token>, leaf: F) -> Result<Vec<Value<N>>, &'static str>{
+        let mut output: Vec<Value<N>> = Vec::new();
+        let mut stack: Vec<Pending> = Vec::new();
+        let mut expect_operand = true;
+        let mut last_was_leaf = false;
+        let mut tokens = tokens.into_iter().peekable();
+
+        while let Some(token) = tokens.next(){
+            let after_leaf = std::mem::replace(&mut last_was_leaf, matches!(token, Token::Number(_) | Token::Ident(_)));
+            match token{
+                Token::Number(num) => {
+                    if !expect_operand{
+                        return Err("Missing operator");
+                    }
+                    output.push(Value::Value(leaf(&num)?));
+                    expect_operand = false;
                 },
-                '5' => {
-                    temp.push_str("5");
-                    continue;
+                Token::Ident(name) => {
+                    // A name right after a number or another name multiplies, as in 3 m, 2 x or kg m^2.
+                    if !expect_operand && after_leaf && !matches!(tokens.peek(), Some(Token::LParen)){
+                        push_operator(Operator::Mult, &mut stack, &mut output);
+                        expect_operand = true;
+                    }
+                    if !expect_operand{
+                        return Err("Missing operator");
+                    }
+                    if let Some(Token::LParen) = tokens.peek(){
+                        match (Function::from_name(&name), Aggregate::from_name(&name)){
+                            (Some(func), _) => stack.push(Pending::Function(func, 0)),
+                            (_, Some(agg)) => stack.push(Pending::Aggregate(agg, 0)),
+                            _ => return Err("Unknown function")
+                        }
+                    }else{
+                        output.push(Value::Variable(name));
+                        expect_operand = false;
+                    }
                 },
-                '6' => {
-                    temp.push_str("6");
-                    continue;
+                Token::LParen => {
+                    if !expect_operand{
+                        return Err("Missing operator");
+                    }
+                    stack.push(Pending::LParen);
                 },
-                '7' => {
-                    temp.push_str("7");
-                    continue;
+                Token::RParen => {
+                    if expect_operand{
+                        return Err("Missing operand");
+                    }
+                    loop{
+                        match stack.pop(){
+                            Some(Pending::LParen) => break,
+                            Some(Pending::Operator(op)) => output.push(Value::Operator(op)),
+                            Some(Pending::Prefix(func)) => output.push(Value::Function(func)),
+                            _ => return Err("Unbalanced parenthesis")
+                        }
+                    }
+                    match stack.last(){
+                        Some(Pending::Function(func, commas)) if commas + 1 == func.arity() => {
+                            output.push(Value::Function(*func));
+                            stack.pop();
+                        },
+                        Some(Pending::Function(_, _)) => return Err("Wrong number of arguments"),
+                        Some(Pending::Aggregate(agg, 3)) => {
+                            output.push(Value::Aggregate(*agg));
+                            stack.pop();
+                        },
+                        Some(Pending::Aggregate(_, _)) => return Err("Wrong number of arguments"),
+                        _ => ()
+                    }
                 },
-                '8' => {
-                    temp.push_str("8");
-                    continue;
+                // Aggregates take (body, variable, from, to), the variable goes to the output as is.
+                Token::Comma => {
+                    if expect_operand{
+                        return Err("Missing operand");
+                    }
+                    loop{
+                        match stack.last(){
+                            Some(Pending::LParen) => break,
+                            Some(Pending::Operator(op)) => output.push(Value::Operator(*op)),
+                            Some(Pending::Prefix(func)) => output.push(Value::Function(*func)),
+                            _ => return Err("Unexpected ','")
+                        }
+                        stack.pop();
+                    }
+                    let commas = match stack.len().checked_sub(2).and_then(|i| stack.get_mut(i)){
+                        Some(Pending::Function(func, commas)) if *commas + 1 < func.arity() => {
+                            *commas += 1;
+                            expect_operand = true;
+                            continue;
+                        },
+                        Some(Pending::Aggregate(_, commas)) if *commas < 3 => commas,
+                        _ => return Err("Unexpected ','")
+                    };
+                    *commas += 1;
+                    if *commas == 1{
+                        match (tokens.next(), tokens.next()){
+                            (Some(Token::Ident(name)), Some(Token::Comma)) => output.push(Value::Variable(name)),
+                            _ => return Err("Expected a variable name")
+                        }
+                        *commas += 1;
+                    }
+                    expect_operand = true;
                 },
-                '9' => {
-                    temp.push_str("9");
-                    continue;
+                // Postfix, binds tighter than anything still pending.
+                Token::Bang => {
+                    if expect_operand{
+                        return Err("Missing operand");
+                    }
+                    output.push(Value::Function(Function::Fact));
                 },
-                '.' => {
-                    temp.push_str(".");
-                    continue;
+                Token::Tilde => {
+                    if !expect_operand{
+                        return Err("Missing operator");
+                    }
+                    stack.push(Pending::Prefix(Function::Not));
                 },
-                _ => ()
-            };
-            if temp.eq(".") {
-                return Err("Bad float");
+                Token::Operator(op) => {
+                    if expect_operand{
+                        match op{
+                            Operator::Minus => stack.push(Pending::Prefix(Function::Neg)),
+                            Operator::Plus => (),
+                            _ => return Err("Missing operand")
+                        }
+                        continue;
+                    }
+                    push_operator(op, &mut stack, &mut output);
+                    expect_operand = true;
+                }
             }
-            
-            println!("{}",&temp);
+        }
+        if expect_operand{
+            return Err("Missing operand");
+        }
+        while let Some(pending) = stack.pop(){
+            match pending{
+                Pending::Operator(op) => output.push(Value::Operator(op)),
+                Pending::Prefix(func) => output.push(Value::Function(func)),
+                _ => return Err("Unbalanced parenthesis")
+            }
+        }
+        Ok(output)
+    }
 
-            temp.truncate(0);
-            
-            match c{
-                '+' => {
-                    
+    // Builds a tree from values in postfix order, shared by the infix and RPN front ends.
+    pub fn tree_from_postfix<N>(values: Vec<Value<N>>) -> Result<Tree<Value<N>>, &'static str>{
+        let mut stack: Vec<Box<Node<Value<N>>>> = Vec::new();
+        for value in values{
+            match value{
+                Value::Value(_) | Value::Variable(_) => stack.push(Box::new(Node::new(value))),
+                Value::Operator(_) => {
+                    match (stack.pop(), stack.pop()){
+                        (Some(right), Some(left)) => {
+                            stack.push(Node::with_children(value, Some(left), Some(right)));
+                        },
+                        _ => return Err("Missing operand")
+                    }
                 },
-                '-' => println!("{}",c),
-                '*' => println!("{}",c),
-                '/' => println!("{}",c),
-                _ => {return Err("Wrong input")}
-            };
-
-            
+                Value::Function(func) if func.arity() == 2 => {
+                    match (stack.pop(), stack.pop()){
+                        (Some(right), Some(left)) => {
+                            stack.push(Node::with_children(value, Some(left), Some(right)));
+                        },
+                        _ => return Err("Missing operand")
+                    }
+                },
+                Value::Function(_) => {
+                    match stack.pop(){
+                        Some(arg) => stack.push(Node::with_children(value, Some(arg), None)),
+                        None => return Err("Missing operand")
+                    }
+                },
+                Value::Aggregate(_) => {
+                    let (to, from, var, body) = match (stack.pop(), stack.pop(), stack.pop(), stack.pop()){
+                        (Some(to), Some(from), Some(var), Some(body)) => (to, from, var, body),
+                        _ => return Err("Missing operand")
+                    };
+                    let name = match &var.value{
+                        Some(Value::Variable(name)) => name.clone(),
+                        _ => return Err("Expected a variable name")
+                    };
+                    let range = Node::with_children(Value::Range(name), Some(from), Some(to));
+                    stack.push(Node::with_children(value, Some(body), Some(range)));
+                },
+                Value::Range(_) => return Err("Wrong input")
+            }
+        }
+        match (stack.pop(), stack.is_empty()){
+            (Some(root), true) => Ok(Tree::from_root(root)),
+            (None, _) => Err("Empty expression"),
+            _ => Err("Missing operator")
         }
-        println!("{}",&temp);
+    }
 
+    pub fn parse_to_tree(src: &str) -> Result<Tree<Value>, &'static str>{
+        parse_to_tree_with(src, parse_number)
+    }
+
+    // Same grammar as parse_to_tree with the numeric literals read by leaf.
+    pub fn parse_to_tree_with<N, F: Fn(&str) -> Result<N, &'static str>>(src: &str, leaf: F) -> Result<Tree<Value<N>>, &'static str>{
+        parse_to_tree_with_literals(src, Literals::default(), leaf)
+    }
 
-        Ok(Node{
-            up: None,
-            left: None,
-            value: Some(Value::Value(0f64)),
-            right: None
-        })
+    // For modes that read more kinds of literal than plain numbers.
+    pub fn parse_to_tree_with_literals<N, F: Fn(&str) -> Result<N, &'static str>>(src: &str, literals: Literals, leaf: F) -> Result<Tree<Value<N>>, &'static str>{
+        tree_from_postfix(to_postfix(tokenize(src, literals)?, leaf)?)
     }
 
     pub fn test() -> Result<(), TreeError>{
@@ -852,4 +1752,98 @@ pub mod model{
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_rational::rational::Rational;
+
+        #[test]
+        fn walkers_agree_on_rationals(){
+            for src in ["1/3 + 1/6", "2 - 3 * 5 / 7", "(1 + 2) * (3 - 4 / 5)", "2 ^ 10 / 3"]{
+                let mut tree = parse_to_tree_with(src, Rational::from_literal).unwrap();
+                let expected = tree.evaluate_number(&HashMap::new()).unwrap();
+                assert_eq!(tree.evaluate_it3().unwrap(), expected, "{}", src);
+                assert_eq!(tree.evaluate_it2().unwrap(), expected, "{}", src);
+                assert_eq!(tree.evaluate_it1().unwrap(), expected, "{}", src);
+            }
+        }
+
+        #[test]
+        fn walkers_agree_on_floats(){
+            let shapes = ["7", "1 + 2", "1 * 2 + 3", "(1 + 2) * (3 + 4)", "((1 + 2) + 3) + 4",
+                "(1 - 2) - (3 - (4 - 5))", "2 - 3 * 5 / 7", "2 ^ (1 / 2) * 3"];
+            for src in shapes{
+                let mut tree = parse_to_tree(src).unwrap();
+                let expected = tree.rec_evaluate().unwrap();
+                assert_eq!(tree.evaluate_it1().unwrap(), expected, "{}", src);
+                assert_eq!(tree.evaluate_it2().unwrap(), expected, "{}", src);
+                assert_eq!(tree.evaluate_it3().unwrap(), expected, "{}", src);
+            }
+            for src in shapes{
+                let mut tree = parse_to_tree_with(src, Rational::from_literal).unwrap();
+                match tree.evaluate_number(&HashMap::new()){
+                    Ok(expected) => {
+                        assert_eq!(tree.evaluate_it1().unwrap(), expected, "{}", src);
+                        assert_eq!(tree.evaluate_it2().unwrap(), expected, "{}", src);
+                        assert_eq!(tree.evaluate_it3().unwrap(), expected, "{}", src);
+                    },
+                    Err(_) => assert!(tree.evaluate_it2().is_err(), "{}", src)
+                }
+            }
+        }
+
+        #[test]
+        fn plus_minus_is_two_operators_by_default(){
+            assert_eq!(parse_to_tree("1+-2").unwrap().rec_evaluate().unwrap(), -1f64);
+            assert_eq!(parse_to_tree("1 + -2").unwrap().rec_evaluate().unwrap(), -1f64);
+            assert!(matches!(parse_to_tree("1 ± 2"), Err("Bad float")));
+        }
+
+        #[test]
+        fn walkers_report_exact_division_by_zero(){
+            let mut tree = parse_to_tree_with("1 / (2 - 2)", Rational::from_literal).unwrap();
+            assert!(matches!(tree.evaluate_it3(), Err(EvalError::DivisionByZero)));
+            assert!(matches!(tree.evaluate_it1(), Err(EvalError::DivisionByZero)));
+            assert!(matches!(tree.evaluate_it2(), Err(EvalError::DivisionByZero)));
+        }
+
+        #[test]
+        fn radix_and_separated_literals(){
+            let evaluate = |src: &str| parse_to_tree(src).unwrap().rec_evaluate().unwrap();
+            assert_eq!(evaluate("0xFF.8"), 255.5);
+            assert_eq!(evaluate("0b1010 + 0o17"), 25f64);
+            assert_eq!(evaluate("0xff_ff"), 65535f64);
+            assert_eq!(evaluate("1_000"), 1000f64);
+            assert_eq!(evaluate("1e3"), 1000f64);
+        }
+
+        #[test]
+        fn malformed_literals(){
+            for src in ["0x", "1_", "1__0", "1e", "1e+"]{
+                assert!(matches!(parse_to_tree(src), Err("Bad literal")), "{}", src);
+            }
+            assert!(matches!(parse_to_tree("0b102"), Err("Bad digit")));
+            assert!(matches!(parse_to_tree("2exp").unwrap().rec_evaluate(), Err(EvalError::UnknownVariable(_))));
+        }
+
+        #[test]
+        fn layouts_render_to_any_writer(){
+            let tree = parse_to_tree("(1 - x) * -sin(y)").unwrap();
+            let mut rotated = String::new();
+            tree.render(&mut rotated, Layout::Rotated).unwrap();
+            assert_eq!(rotated, "   neg\n      sin\n         y\n*\n      x\n   -\n      1\n");
+
+            let mut boxed = Vec::new();
+            tree.render_io(&mut boxed, Layout::Boxed).unwrap();
+            assert_eq!(String::from_utf8(boxed).unwrap(), "*\n├── -\n│   ├── 1\n│   └── x\n└── neg\n    └── sin\n        └── y\n");
+
+            let mut leaf = String::new();
+            parse_to_tree("7").unwrap().render(&mut leaf, Layout::Boxed).unwrap();
+            assert_eq!(leaf, "7\n");
+            let mut empty = String::new();
+            Tree::<Value>::new().render(&mut empty, Layout::Rotated).unwrap();
+            assert_eq!(empty, "");
+        }
+    }
+}
@@ -0,0 +1,296 @@
+#[allow(dead_code)]
+pub mod number{
+    use std::fmt;
+    use crate::calculator_calculus::calculus;
+    use crate::calculator_model::model::{
+        parse_number,
+        Aggregate,
+        EvalError,
+        Function,
+        Operator
+    };
+
+    // Arithmetic a Tree<Value<N>> can be evaluated over.
+    pub trait Number: Clone + fmt::Display{
+        fn from_literal(src: &str) -> Result<Self, &'static str>;
+        fn zero() -> Self;
+        fn one() -> Self;
+        fn to_f64(&self) -> f64;
+        fn add(&self, other: &Self) -> Result<Self, EvalError>;
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>;
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>;
+        fn div(&self, other: &Self) -> Result<Self, EvalError>;
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>;
+
+        // Functions without a result in this type are NotExact.
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            match func{
+                Function::Neg => Self::zero().sub(self),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+
+        fn call_binary(&self, _func: Function, _other: &Self) -> Result<Self, EvalError>{
+            Err(EvalError::NotExact)
+        }
+
+        // &, |, xor, << and >>, which only integer types have.
+        fn bitwise(&self, _op: Operator, _other: &Self) -> Result<Self, EvalError>{
+            Err(EvalError::NotExact)
+        }
+
+        // Sums and products step by one, integrals are left to types that can approximate.
+        fn aggregate<F: FnMut(&Self) -> Result<Self, EvalError>>(agg: Aggregate, from: &Self, to: &Self, mut body: F) -> Result<Self, EvalError>{
+            let count = calculus::terms(from.to_f64(), to.to_f64())?;
+            let mut total = match agg{
+                Aggregate::Sum => Self::zero(),
+                Aggregate::Product => Self::one(),
+                Aggregate::Integrate => return Err(EvalError::NotExact)
+            };
+            let mut x = from.clone();
+            for k in 0..count{
+                if k > 0{
+                    x = x.add(&Self::one())?;
+                }
+                let term = body(&x)?;
+                total = if agg == Aggregate::Sum {total.add(&term)?} else {total.mul(&term)?};
+            }
+            Ok(total)
+        }
+    }
+
+    impl Number for f64{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            parse_number(src)
+        }
+
+        fn zero() -> Self{
+            0f64
+        }
+
+        fn one() -> Self{
+            1f64
+        }
+
+        fn to_f64(&self) -> f64{
+            *self
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self + other)
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self - other)
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self * other)
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self / other)
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.powf(*other))
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            Ok(func.evaluate(*self))
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            Ok(func.evaluate_binary(*self, *other))
+        }
+
+        fn bitwise(&self, op: Operator, other: &Self) -> Result<Self, EvalError>{
+            Ok(op.evaluate(*self, *other))
+        }
+
+        // A bare f64 has nowhere to keep an integral's error estimate, Quadrature carries it.
+        fn aggregate<F: FnMut(&Self) -> Result<Self, EvalError>>(agg: Aggregate, from: &Self, to: &Self, mut body: F) -> Result<Self, EvalError>{
+            Ok(agg.evaluate(*from, *to, |x| body(&x))?.value)
+        }
+    }
+
+    impl Number for f32{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            parse_number(src)?;
+            src.parse().map_err(|_| "Bad float")
+        }
+
+        fn zero() -> Self{
+            0f32
+        }
+
+        fn one() -> Self{
+            1f32
+        }
+
+        fn to_f64(&self) -> f64{
+            *self as f64
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self + other)
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self - other)
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self * other)
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self / other)
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(self.powf(*other))
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            Ok(match func{
+                Function::Sin => self.sin(),
+                Function::Cos => self.cos(),
+                Function::Tan => self.tan(),
+                Function::Exp => self.exp(),
+                Function::Ln => self.ln(),
+                Function::Sqrt => self.sqrt(),
+                Function::Abs => self.abs(),
+                Function::Neg => -self,
+                _ => func.evaluate(*self as f64) as f32
+            })
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            Ok(func.evaluate_binary(*self as f64, *other as f64) as f32)
+        }
+
+        fn bitwise(&self, op: Operator, other: &Self) -> Result<Self, EvalError>{
+            Ok(op.evaluate(*self as f64, *other as f64) as f32)
+        }
+
+        // Integrates in f64 and rounds the result.
+        fn aggregate<F: FnMut(&Self) -> Result<Self, EvalError>>(agg: Aggregate, from: &Self, to: &Self, mut body: F) -> Result<Self, EvalError>{
+            let total = agg.evaluate(*from as f64, *to as f64, |x| Ok(body(&(x as f32))? as f64))?;
+            Ok(total.value as f32)
+        }
+    }
+
+    // Integer arithmetic that reports overflow instead of wrapping, division truncates.
+    impl Number for i64{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            if src.is_empty() || !src.bytes().all(|b| b.is_ascii_digit()){
+                return Err("Bad integer");
+            }
+            src.parse().map_err(|_| "Bad integer")
+        }
+
+        fn zero() -> Self{
+            0
+        }
+
+        fn one() -> Self{
+            1
+        }
+
+        fn to_f64(&self) -> f64{
+            *self as f64
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            self.checked_add(*other).ok_or(EvalError::Overflow)
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            self.checked_sub(*other).ok_or(EvalError::Overflow)
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            self.checked_mul(*other).ok_or(EvalError::Overflow)
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            if *other == 0{
+                return Err(EvalError::DivisionByZero);
+            }
+            self.checked_div(*other).ok_or(EvalError::Overflow)
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            match u32::try_from(*other){
+                Ok(exp) => self.checked_pow(exp).ok_or(EvalError::Overflow),
+                Err(_) if *other < 0 => Err(EvalError::NotExact),
+                Err(_) => Err(EvalError::Overflow)
+            }
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            match func{
+                Function::Neg => self.checked_neg().ok_or(EvalError::Overflow),
+                Function::Abs => self.checked_abs().ok_or(EvalError::Overflow),
+                Function::Fact => {
+                    if *self < 0{
+                        return Err(EvalError::NotExact);
+                    }
+                    (2..=*self).try_fold(1i64, |acc, i| acc.checked_mul(i)).ok_or(EvalError::Overflow)
+                },
+                Function::Not => Ok(!self),
+                Function::Popcount => Ok(self.count_ones() as i64),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+
+        // Each partial product is a binomial coefficient no larger than the result, so i128 never overflows and the division is exact.
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            let (n, k) = (*self, *other);
+            match func{
+                Function::Binom if n < 0 || k < 0 => Err(EvalError::NotExact),
+                Function::Binom if k > n => Ok(0),
+                Function::Binom => {
+                    let k = k.min(n - k);
+                    (1..=k).try_fold(1i64, |acc, i| i64::try_from(acc as i128 * (n - k + i) as i128 / i as i128).ok())
+                        .ok_or(EvalError::Overflow)
+                },
+                Function::Rotl => Ok(self.rotate_left(k.rem_euclid(64) as u32)),
+                Function::Rotr => Ok(self.rotate_right(k.rem_euclid(64) as u32)),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+
+        // Shifts past the width are errors rather than wrapping the count.
+        fn bitwise(&self, op: Operator, other: &Self) -> Result<Self, EvalError>{
+            let shift = u32::try_from(*other).ok().filter(|n| *n < 64);
+            match op{
+                Operator::And => Ok(self & other),
+                Operator::Or => Ok(self | other),
+                Operator::Xor => Ok(self ^ other),
+                Operator::Shl => shift.map(|n| (self << n, n)).filter(|(out, n)| out >> n == *self)
+                    .map(|(out, _)| out).ok_or(EvalError::Overflow),
+                Operator::Shr => shift.map(|n| self >> n).ok_or(EvalError::Overflow),
+                _ => op.apply(self, other)
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+
+        #[test]
+        fn shifts_keep_the_sign_and_magnitude(){
+            assert_eq!(Number::bitwise(&1i64, Operator::Shl, &62).unwrap(), 1 << 62);
+            assert_eq!(Number::bitwise(&-1i64, Operator::Shl, &63).unwrap(), i64::MIN);
+            assert_eq!(Number::bitwise(&-3i64, Operator::Shl, &2).unwrap(), -12);
+            assert!(matches!(Number::bitwise(&1i64, Operator::Shl, &63), Err(EvalError::Overflow)));
+            assert!(matches!(Number::bitwise(&3i64, Operator::Shl, &62), Err(EvalError::Overflow)));
+            assert!(matches!(Number::bitwise(&i64::MIN, Operator::Shl, &1), Err(EvalError::Overflow)));
+            assert!(matches!(Number::bitwise(&1i64, Operator::Shl, &64), Err(EvalError::Overflow)));
+            assert_eq!(Number::bitwise(&-8i64, Operator::Shr, &1).unwrap(), -4);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+#[allow(dead_code)]
+pub mod rational{
+    use std::{
+        cmp::Ordering,
+        collections::HashMap,
+        fmt
+    };
+    use crate::calculator_bigint::bigint::{
+        scale,
+        BigInt
+    };
+    use crate::calculator_model::model::{
+        EvalError,
+        Function,
+        Tree,
+        Value
+    };
+    use crate::calculator_number::number::Number;
+
+    // Integer powers whose result would need more bits than this are rejected.
+    const MAX_BITS: u64 = 1 << 24;
+
+    // Always in lowest terms with a positive denominator.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Rational{
+        num: BigInt,
+        den: BigInt
+    }
+
+    impl Rational{
+        pub fn new(num: BigInt, den: BigInt) -> Option<Rational>{
+            if den.is_zero(){
+                return None;
+            }
+            let (num, den) = if den.is_negative() {(-&num, -&den)} else {(num, den)};
+            let gcd = num.gcd(&den);
+            if gcd.is_zero() || gcd.is_one(){
+                return Some(Rational{num, den});
+            }
+            match (num.div_rem(&gcd), den.div_rem(&gcd)){
+                (Some((num, _)), Some((den, _))) => Some(Rational{num, den}),
+                _ => None
+            }
+        }
+
+        pub fn from_integer(num: BigInt) -> Rational{
+            Rational{num, den: BigInt::one()}
+        }
+
+        pub fn zero() -> Rational{
+            Rational::from_integer(BigInt::zero())
+        }
+
+        pub fn one() -> Rational{
+            Rational::from_integer(BigInt::one())
+        }
+
+        // Decimal literal like "-12.375", no exponent.
+        pub fn parse_decimal(src: &str) -> Option<Rational>{
+            let (whole, frac) = src.split_once('.').unwrap_or((src, ""));
+            let num = BigInt::parse(&format!("{}{}", whole, frac))?;
+            Rational::new(num, BigInt::from(10u64).pow(frac.len() as u64))
+        }
+
+        pub fn numer(&self) -> &BigInt{
+            &self.num
+        }
+
+        pub fn denom(&self) -> &BigInt{
+            &self.den
+        }
+
+        pub fn is_zero(&self) -> bool{
+            self.num.is_zero()
+        }
+
+        pub fn is_integer(&self) -> bool{
+            self.den.is_one()
+        }
+
+        pub fn add(&self, other: &Rational) -> Rational{
+            let num = &(&self.num * &other.den) + &(&other.num * &self.den);
+            Rational::reduced(num, &self.den * &other.den)
+        }
+
+        pub fn sub(&self, other: &Rational) -> Rational{
+            self.add(&other.neg())
+        }
+
+        pub fn mul(&self, other: &Rational) -> Rational{
+            Rational::reduced(&self.num * &other.num, &self.den * &other.den)
+        }
+
+        pub fn div(&self, other: &Rational) -> Result<Rational, EvalError>{
+            match Rational::new(&self.num * &other.den, &self.den * &other.num){
+                Some(quotient) => Ok(quotient),
+                None => Err(EvalError::DivisionByZero)
+            }
+        }
+
+        pub fn neg(&self) -> Rational{
+            Rational{num: -&self.num, den: self.den.clone()}
+        }
+
+        pub fn abs(&self) -> Rational{
+            Rational{num: self.num.abs(), den: self.den.clone()}
+        }
+
+        // Only integer exponents have exact results.
+        pub fn pow(&self, exp: &Rational) -> Result<Rational, EvalError>{
+            if !exp.is_integer(){
+                return Err(EvalError::NotExact);
+            }
+            let exp = match exp.num.to_i64(){
+                Some(exp) => exp,
+                None => return Err(EvalError::Overflow)
+            };
+            let bits = self.num.bits().max(self.den.bits());
+            if bits > 1 && bits.saturating_mul(exp.unsigned_abs()) > MAX_BITS{
+                return Err(EvalError::Overflow);
+            }
+            let power = Rational{
+                num: self.num.pow(exp.unsigned_abs()),
+                den: self.den.pow(exp.unsigned_abs())
+            };
+            if exp < 0 {Rational::one().div(&power)} else {Ok(power)}
+        }
+
+        // Non-negative whole numbers only, for factorials and binomials.
+        fn to_count(&self) -> Result<u64, EvalError>{
+            if !self.is_integer(){
+                return Err(EvalError::NotExact);
+            }
+            self.num.to_u64().ok_or(EvalError::NotExact)
+        }
+
+        fn reduced(num: BigInt, den: BigInt) -> Rational{
+            Rational::new(num, den).unwrap_or_else(Rational::zero)
+        }
+
+        // Nearest f64, scaling both sides first so huge numerators and denominators don't overflow.
+        pub fn to_f64(&self) -> f64{
+            if self.num.is_zero(){
+                return 0f64;
+            }
+            let shift = 64 - (self.num.bits() as i64 - self.den.bits() as i64);
+            let (num, den) = if shift >= 0{
+                (self.num.shifted_left(shift as u64), self.den.clone())
+            }else{
+                (self.num.clone(), self.den.shifted_left(-shift as u64))
+            };
+            match num.div_rem(&den){
+                Some((q, _)) => scale(q.to_f64(), -shift.clamp(-(i32::MAX as i64), i32::MAX as i64) as i32),
+                None => f64::NAN
+            }
+        }
+
+        // Rounded half away from zero to at most `places` digits, trailing zeros dropped.
+        pub fn to_decimal(&self, places: usize) -> String{
+            let unit = BigInt::from(10u64).pow(places as u64);
+            let scaled = &(&self.num.abs() * &unit) + &self.den.shifted_right(1);
+            let digits = match scaled.div_rem(&self.den){
+                Some((q, _)) => q.to_string(),
+                None => return "NaN".to_string()
+            };
+            let digits = format!("{:0>width$}", digits, width = places + 1);
+            let (whole, frac) = digits.split_at(digits.len() - places);
+            let frac = frac.trim_end_matches('0');
+            let sign = if self.num.is_negative() && digits.bytes().any(|b| b != b'0') {"-"} else {""};
+            if frac.is_empty(){
+                format!("{}{}", sign, whole)
+            }else{
+                format!("{}{}.{}", sign, whole, frac)
+            }
+        }
+    }
+
+    impl Ord for Rational{
+        fn cmp(&self, other: &Self) -> Ordering{
+            (&self.num * &other.den).cmp(&(&other.num * &self.den))
+        }
+    }
+
+    impl PartialOrd for Rational{
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering>{
+            Some(self.cmp(other))
+        }
+    }
+
+    impl fmt::Display for Rational{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            if self.is_integer(){
+                write!(f, "{}", self.num)
+            }else{
+                write!(f, "{}/{}", self.num, self.den)
+            }
+        }
+    }
+
+    impl Number for Rational{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            Rational::parse_decimal(src).ok_or("Bad rational")
+        }
+
+        fn zero() -> Self{
+            Rational::zero()
+        }
+
+        fn one() -> Self{
+            Rational::one()
+        }
+
+        fn to_f64(&self) -> f64{
+            Rational::to_f64(self)
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Rational::add(self, other))
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Rational::sub(self, other))
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Rational::mul(self, other))
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            Rational::div(self, other)
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Rational::pow(self, other)
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            match func{
+                Function::Neg => Ok(self.neg()),
+                Function::Abs => Ok(self.abs()),
+                Function::Fact => Ok(Rational::from_integer(BigInt::factorial(self.to_count()?)?)),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            match func{
+                Function::Binom => Ok(Rational::from_integer(BigInt::binomial(self.to_count()?, other.to_count()?)?)),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+    }
+
+    // How `/` behaves when evaluating over big integers.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum IntegerDivision{
+        Rational,
+        Error
+    }
+
+    impl IntegerDivision{
+        pub fn from_name(name: &str) -> Option<IntegerDivision>{
+            match name{
+                "rational" => Some(IntegerDivision::Rational),
+                "error" => Some(IntegerDivision::Error),
+                _ => None
+            }
+        }
+    }
+
+    impl Tree<Value<BigInt>>{
+        // Uneven division is NotExact unless it may leave the integers for a fraction.
+        pub fn evaluate_integer(&self, division: IntegerDivision) -> Result<Rational, EvalError>{
+            let root = match self.root_node(){
+                Some(root) => root,
+                None => return Err(EvalError::BadTree)
+            };
+            match division{
+                IntegerDivision::Error => Ok(Rational::from_integer(root.evaluate_number(&HashMap::new())?)),
+                IntegerDivision::Rational => {
+                    let exact_root = root.try_map(&|num: &BigInt| Ok::<_, EvalError>(Rational::from_integer(num.clone())))?;
+                    exact_root.evaluate_number(&HashMap::new())
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree_with;
+
+        fn exact(src: &str) -> Result<Rational, EvalError>{
+            parse_to_tree_with(src, Rational::from_literal).unwrap().evaluate_number(&HashMap::new())
+        }
+
+        #[test]
+        fn decimal_literals_are_exact(){
+            assert_eq!(exact("0.1 + 0.2").unwrap().to_string(), "3/10");
+            assert_eq!(exact("1 / 3 * 3").unwrap().to_string(), "1");
+            assert_eq!(exact("2 ^ -2").unwrap().to_string(), "1/4");
+            assert_eq!(exact("0.10000000000000000001 - 0.1").unwrap().to_string(), "1/100000000000000000000");
+            assert_eq!(exact("0xFF + 1e-3").unwrap().to_string(), "255001/1000");
+        }
+
+        #[test]
+        fn stays_in_lowest_terms(){
+            let half = Rational::new(BigInt::parse("-6").unwrap(), BigInt::parse("-12").unwrap()).unwrap();
+            assert_eq!(half.to_string(), "1/2");
+            assert!(Rational::new(BigInt::one(), BigInt::zero()).is_none());
+        }
+
+        #[test]
+        fn refuses_what_it_cannot_do_exactly(){
+            assert!(matches!(exact("1 / (3 - 3)"), Err(EvalError::DivisionByZero)));
+            assert!(matches!(exact("2 ^ 0.5"), Err(EvalError::NotExact)));
+            assert!(matches!(exact("sin(1)"), Err(EvalError::NotExact)));
+        }
+
+        #[test]
+        fn decimal_output_rounds_half_away_from_zero(){
+            assert_eq!(exact("2 / 3").unwrap().to_decimal(3), "0.667");
+            assert_eq!(exact("-1 / 8").unwrap().to_decimal(2), "-0.13");
+            assert_eq!(exact("1 / 4").unwrap().to_decimal(5), "0.25");
+        }
+    }
+}
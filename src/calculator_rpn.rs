@@ -0,0 +1,132 @@
+#[allow(dead_code)]
+pub mod rpn{
+    use crate::calculator_model::model::{
+        parse_number,
+        tree_from_postfix,
+        Aggregate,
+        Function,
+        Node,
+        Operator,
+        Tree,
+        Value
+    };
+
+    fn parse_word(word: &str) -> Result<Value, &'static str>{
+        if let Some(op) = Operator::from_symbol(word){
+            return Ok(Value::Operator(op));
+        }
+        if let Some(func) = Function::from_name(word){
+            return Ok(Value::Function(func));
+        }
+        if let Some(agg) = Aggregate::from_name(word){
+            return Ok(Value::Aggregate(agg));
+        }
+        let digits = word.strip_prefix('-').unwrap_or(word);
+        match digits.chars().next(){
+            Some('0'..='9') | Some('.') => Ok(Value::Value(parse_number(word)?)),
+            Some(c) if (c.is_ascii_alphabetic() || c == '_') && word == digits => {
+                if word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'){
+                    Ok(Value::Variable(word.to_string()))
+                }else{
+                    Err("Wrong input")
+                }
+            },
+            _ => Err("Wrong input")
+        }
+    }
+
+    // Words are separated by whitespace, a leading '-' on a number makes it negative.
+    // Aggregates take four words before them: body, variable, from and to.
+    pub fn parse_rpn(src: &str) -> Result<Tree<Value>, &'static str>{
+        let mut values = Vec::new();
+        for word in src.split_whitespace(){
+            values.push(parse_word(word)?);
+        }
+        tree_from_postfix(values)
+    }
+
+    fn write_rpn(node: &Node<Value>, out: &mut Vec<String>){
+        match &node.value{
+            Some(Value::Operator(_)) => {
+                for child in [node.left_node(), node.right_node()]{
+                    match child{
+                        Some(n) => write_rpn(n, out),
+                        None => out.push("0".to_string())
+                    }
+                }
+            },
+            Some(Value::Function(func)) => {
+                if let Some(n) = node.left_node(){
+                    write_rpn(n, out);
+                }
+                if func.arity() == 2{
+                    match node.right_node(){
+                        Some(n) => write_rpn(n, out),
+                        None => out.push("0".to_string())
+                    }
+                }
+            },
+            Some(Value::Aggregate(_)) => {
+                for child in [node.left_node(), node.right_node()].into_iter().flatten(){
+                    write_rpn(child, out);
+                }
+            },
+            Some(Value::Range(name)) => {
+                out.push(name.clone());
+                for child in [node.left_node(), node.right_node()].into_iter().flatten(){
+                    write_rpn(child, out);
+                }
+                return;
+            },
+            _ => ()
+        }
+        match &node.value{
+            Some(val) => out.push(val.to_string()),
+            None => out.push("None".to_string())
+        }
+    }
+
+    impl Tree<Value>{
+        pub fn to_rpn(&self) -> String{
+            let mut out = Vec::new();
+            if let Some(root) = self.root_node(){
+                write_rpn(root, &mut out);
+            }
+            out.join(" ")
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree;
+
+        #[test]
+        fn infix_round_trips_through_rpn(){
+            for src in ["1 - (2 - 3)", "2^3^2", "(2^3)^2", "-(1 + 2) * 3", "binom(n, 2) + 3!", "integrate(x^2, x, 0, 1)", "sum(k, k, 1, -4)"]{
+                let rpn = parse_to_tree(src).unwrap().to_rpn();
+                let tree = parse_rpn(&rpn).unwrap();
+                assert_eq!(tree.to_rpn(), rpn, "{}", src);
+                assert_eq!(tree.to_string(), src);
+            }
+        }
+
+        #[test]
+        fn words_are_postfix(){
+            assert_eq!(parse_to_tree("a / (b * c) - x").unwrap().to_rpn(), "a b c * / x -");
+            assert_eq!(parse_rpn("x neg 2 ^").unwrap().to_string(), "(-x)^2");
+            assert_eq!(parse_rpn("-1.5 2 *").unwrap().rec_evaluate().unwrap(), -3f64);
+            assert_eq!(parse_rpn("  3\t4\n+ ").unwrap().rec_evaluate().unwrap(), 7f64);
+        }
+
+        #[test]
+        fn malformed_programs(){
+            assert!(matches!(parse_rpn("1 +"), Err("Missing operand")));
+            assert!(matches!(parse_rpn("1 2"), Err("Missing operator")));
+            assert!(matches!(parse_rpn("1 2 x y integrate"), Err("Expected a variable name")));
+            assert!(matches!(parse_rpn("1 -x +"), Err("Wrong input")));
+            assert!(matches!(parse_rpn("1 2 $"), Err("Wrong input")));
+            assert!(parse_rpn("").is_err());
+        }
+    }
+}
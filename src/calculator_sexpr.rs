@@ -0,0 +1,219 @@
+#[allow(dead_code)]
+pub mod sexpr{
+    use std::{
+        iter::Peekable,
+        str::Chars
+    };
+    use crate::calculator_model::model::{
+        parse_number,
+        Aggregate,
+        Function,
+        Node,
+        Operator,
+        Tree,
+        Value
+    };
+
+    enum Token{
+        Open,
+        Close,
+        Atom(String)
+    }
+
+    fn tokenize(src: &str) -> Vec<Token>{
+        let mut tokens = Vec::new();
+        let mut chars: Peekable<Chars> = src.chars().peekable();
+        while let Some(c) = chars.next(){
+            match c{
+                '(' => tokens.push(Token::Open),
+                ')' => tokens.push(Token::Close),
+                c if c.is_whitespace() => (),
+                c => {
+                    let mut atom = c.to_string();
+                    while let Some(&d) = chars.peek(){
+                        if d == '(' || d == ')' || d.is_whitespace(){
+                            break;
+                        }
+                        atom.push(d);
+                        chars.next();
+                    }
+                    tokens.push(Token::Atom(atom));
+                }
+            }
+        }
+        tokens
+    }
+
+    fn parse_atom(atom: &str) -> Result<Value, &'static str>{
+        let digits = atom.strip_prefix('-').unwrap_or(atom);
+        match digits.chars().next(){
+            Some('0'..='9') | Some('.') => Ok(Value::Value(parse_number(atom)?)),
+            Some(c) if (c.is_ascii_alphabetic() || c == '_') && atom == digits => {
+                if !atom.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'){
+                    return Err("Wrong input");
+                }
+                if Function::from_name(atom).is_some() || Aggregate::from_name(atom).is_some(){
+                    return Err("Function used as a value");
+                }
+                Ok(Value::Variable(atom.to_string()))
+            },
+            _ => Err("Wrong input")
+        }
+    }
+
+    fn parse_expr(tokens: &mut std::vec::IntoIter<Token>) -> Result<Box<Node<Value>>, &'static str>{
+        match tokens.next(){
+            Some(Token::Atom(atom)) => Ok(Node::with_children(parse_atom(&atom)?, None, None)),
+            Some(Token::Open) => {
+                let head = match tokens.next(){
+                    Some(Token::Atom(head)) => head,
+                    _ => return Err("Expected operator or function")
+                };
+                let mut args = Vec::new();
+                loop{
+                    match tokens.as_slice().first(){
+                        Some(Token::Close) => {
+                            tokens.next();
+                            break;
+                        },
+                        Some(_) => args.push(parse_expr(tokens)?),
+                        None => return Err("Unbalanced parenthesis")
+                    }
+                }
+                // (sum body i from to), the variable must be a plain name
+                if let Some(agg) = Aggregate::from_name(&head){
+                    let [body, var, from, to]: [Box<Node<Value>>; 4] = match args.try_into(){
+                        Ok(args) => args,
+                        Err(_) => return Err("Wrong number of operands")
+                    };
+                    let name = match &var.value{
+                        Some(Value::Variable(name)) => name.clone(),
+                        _ => return Err("Expected a variable name")
+                    };
+                    let range = Node::with_children(Value::Range(name), Some(from), Some(to));
+                    return Ok(Node::with_children(Value::Aggregate(agg), Some(body), Some(range)));
+                }
+                let value = match (Operator::from_symbol(&head), Function::from_name(&head), args.len()){
+                    (Some(Operator::Minus), _, 1) => Value::Function(Function::Neg),
+                    (Some(op), _, 2) => Value::Operator(op),
+                    (_, Some(func), n) if n == func.arity() => Value::Function(func),
+                    (Some(_), _, _) | (_, Some(_), _) => return Err("Wrong number of operands"),
+                    _ => return Err("Unknown operator or function")
+                };
+                let mut args = args.into_iter();
+                let left = args.next();
+                let right = args.next();
+                Ok(Node::with_children(value, left, right))
+            },
+            Some(Token::Close) => Err("Unbalanced parenthesis"),
+            None => Err("Empty expression")
+        }
+    }
+
+    pub fn parse_sexpr(src: &str) -> Result<Tree<Value>, &'static str>{
+        let mut tokens = tokenize(src).into_iter();
+        let root = parse_expr(&mut tokens)?;
+        match tokens.next(){
+            Some(_) => Err("Trailing input"),
+            None => Ok(Tree::from_root(root))
+        }
+    }
+
+    fn write_operand(out: &mut String, node: Option<&Node<Value>>){
+        match node{
+            Some(n) => write_sexpr(out, n),
+            None => out.push('0')
+        }
+    }
+
+    fn write_sexpr(out: &mut String, node: &Node<Value>){
+        match &node.value{
+            Some(Value::Operator(op)) => {
+                out.push_str(&format!("({} ", op));
+                write_operand(out, node.left_node());
+                out.push(' ');
+                write_operand(out, node.right_node());
+                out.push(')');
+            },
+            Some(Value::Function(func)) => {
+                out.push_str(&format!("({} ", func));
+                write_operand(out, node.left_node());
+                if func.arity() == 2{
+                    out.push(' ');
+                    write_operand(out, node.right_node());
+                }
+                out.push(')');
+            },
+            Some(Value::Aggregate(agg)) => {
+                out.push_str(&format!("({} ", agg));
+                write_operand(out, node.left_node());
+                if let Some(range) = node.right_node(){
+                    out.push(' ');
+                    write_sexpr(out, range);
+                }
+                out.push(')');
+            },
+            Some(Value::Range(name)) => {
+                out.push_str(&format!("{} ", name));
+                write_operand(out, node.left_node());
+                out.push(' ');
+                write_operand(out, node.right_node());
+            },
+            Some(val) => out.push_str(&val.to_string()),
+            None => out.push_str("None")
+        }
+    }
+
+    impl Tree<Value>{
+        pub fn to_sexpr(&self) -> String{
+            let mut out = String::new();
+            if let Some(root) = self.root_node(){
+                write_sexpr(&mut out, root);
+            }
+            out
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree;
+
+        #[test]
+        fn trees_round_trip(){
+            for src in ["1 - (2 - x) * 3", "-sin(x)^2 + 3!", "binom(n, 2) / -0.5", "integrate(x^2, x, 0, 1) + product(k, k, 1, n)"]{
+                let sexpr = parse_to_tree(src).unwrap().to_sexpr();
+                let tree = parse_sexpr(&sexpr).unwrap();
+                assert_eq!(tree.to_sexpr(), sexpr, "{}", src);
+                assert_eq!(tree.to_string(), src);
+            }
+            assert_eq!(parse_to_tree("1 - (2 - x) * 3").unwrap().to_sexpr(), "(- 1 (* (- 2 x) 3))");
+            assert_eq!(parse_to_tree("integrate(x^2, x, 0, 1)").unwrap().to_sexpr(), "(integrate (^ x 2) x 0 1)");
+        }
+
+        #[test]
+        fn unary_minus_and_layout(){
+            assert_eq!(parse_sexpr("(- 5)").unwrap().to_sexpr(), "(neg 5)");
+            assert_eq!(parse_sexpr("(- x 1)").unwrap().to_string(), "x - 1");
+            assert_eq!(parse_sexpr("(*\n\t2(-  x 1) )").unwrap().to_string(), "2 * (x - 1)");
+            assert_eq!(parse_sexpr("-2.5").unwrap().rec_evaluate().unwrap(), -2.5);
+        }
+
+        #[test]
+        fn malformed_expressions(){
+            let error = |src: &str| parse_sexpr(src).err();
+            assert_eq!(error(""), Some("Empty expression"));
+            assert_eq!(error("(+ 1 2"), Some("Unbalanced parenthesis"));
+            assert_eq!(error(")"), Some("Unbalanced parenthesis"));
+            assert_eq!(error("(+ 1 2) 3"), Some("Trailing input"));
+            assert_eq!(error("((+) 1 2)"), Some("Expected operator or function"));
+            assert_eq!(error("(+ 1)"), Some("Wrong number of operands"));
+            assert_eq!(error("(sin 1 2)"), Some("Wrong number of operands"));
+            assert_eq!(error("(sum k 1 2)"), Some("Wrong number of operands"));
+            assert_eq!(error("(sum k 1 2 3)"), Some("Expected a variable name"));
+            assert_eq!(error("(foo 1)"), Some("Unknown operator or function"));
+            assert_eq!(error("(+ sin 1)"), Some("Function used as a value"));
+            assert_eq!(error("(+ x$ 1)"), Some("Wrong input"));
+        }
+    }
+}
@@ -0,0 +1,295 @@
+#[allow(dead_code)]
+pub mod simplify{
+    use crate::calculator_model::model::{
+        Aggregate,
+        EvalError,
+        Function,
+        Node,
+        Operator,
+        Tree,
+        Value
+    };
+
+    // Owned form of a Tree<Value>, easier to take apart and rebuild than linked nodes.
+    #[derive(Clone, PartialEq)]
+    pub enum Expr{
+        Num(f64),
+        Var(String),
+        Op(Operator, Box<Expr>, Box<Expr>),
+        Func(Function, Box<Expr>),
+        // Functions of two arguments like binom
+        Func2(Function, Box<Expr>, Box<Expr>),
+        // Aggregate, bound variable, body, from, to
+        Agg(Aggregate, String, Box<Expr>, Box<Expr>, Box<Expr>)
+    }
+
+    impl Expr{
+        pub fn from_tree(tree: &Tree<Value>) -> Result<Expr, EvalError>{
+            match tree.root_node(){
+                Some(root) => Expr::from_node(root),
+                None => Err(EvalError::BadTree)
+            }
+        }
+
+        // A missing operand counts as 0, the same as rec_evaluate.
+        fn from_operand(node: Option<&Node<Value>>) -> Result<Expr, EvalError>{
+            match node{
+                Some(n) => Expr::from_node(n),
+                None => Ok(Expr::Num(0f64))
+            }
+        }
+
+        pub fn from_node(node: &Node<Value>) -> Result<Expr, EvalError>{
+            match &node.value{
+                Some(Value::Value(num)) => Ok(Expr::Num(*num)),
+                Some(Value::Variable(name)) => Ok(Expr::Var(name.clone())),
+                Some(Value::Operator(op)) => {
+                    let left = Expr::from_operand(node.left_node())?;
+                    let right = Expr::from_operand(node.right_node())?;
+                    Ok(Expr::Op(*op, Box::new(left), Box::new(right)))
+                },
+                Some(Value::Function(func)) if func.arity() == 2 => {
+                    match (node.left_node(), node.right_node()){
+                        (Some(left), Some(right)) => Ok(Expr::func2(*func, Expr::from_node(left)?, Expr::from_node(right)?)),
+                        _ => Err(EvalError::BadTree)
+                    }
+                },
+                Some(Value::Function(func)) => {
+                    match node.left_node(){
+                        Some(arg) => Ok(Expr::Func(*func, Box::new(Expr::from_node(arg)?))),
+                        None => Err(EvalError::BadTree)
+                    }
+                },
+                Some(Value::Aggregate(agg)) => {
+                    let (body, range) = match (node.left_node(), node.right_node()){
+                        (Some(body), Some(range)) => (body, range),
+                        _ => return Err(EvalError::BadTree)
+                    };
+                    match (&range.value, range.left_node(), range.right_node()){
+                        (Some(Value::Range(var)), Some(from), Some(to)) => {
+                            Ok(Expr::agg(*agg, var, Expr::from_node(body)?, Expr::from_node(from)?, Expr::from_node(to)?))
+                        },
+                        _ => Err(EvalError::BadTree)
+                    }
+                },
+                Some(Value::Range(_)) | None => Err(EvalError::BadTree)
+            }
+        }
+
+        pub fn to_node(&self) -> Box<Node<Value>>{
+            match self{
+                Expr::Num(num) => Node::with_children(Value::Value(*num), None, None),
+                Expr::Var(name) => Node::with_children(Value::Variable(name.clone()), None, None),
+                Expr::Op(op, left, right) => Node::with_children(Value::Operator(*op), Some(left.to_node()), Some(right.to_node())),
+                Expr::Func(func, arg) => Node::with_children(Value::Function(*func), Some(arg.to_node()), None),
+                Expr::Func2(func, left, right) => Node::with_children(Value::Function(*func), Some(left.to_node()), Some(right.to_node())),
+                Expr::Agg(agg, var, body, from, to) => {
+                    let range = Node::with_children(Value::Range(var.clone()), Some(from.to_node()), Some(to.to_node()));
+                    Node::with_children(Value::Aggregate(*agg), Some(body.to_node()), Some(range))
+                }
+            }
+        }
+
+        pub fn to_tree(&self) -> Tree<Value>{
+            Tree::from_root(self.to_node())
+        }
+
+        pub fn num(&self) -> Option<f64>{
+            match self{
+                Expr::Num(num) => Some(*num),
+                _ => None
+            }
+        }
+
+        pub fn op(op: Operator, left: Expr, right: Expr) -> Expr{
+            Expr::Op(op, Box::new(left), Box::new(right))
+        }
+
+        pub fn func(func: Function, arg: Expr) -> Expr{
+            Expr::Func(func, Box::new(arg))
+        }
+
+        pub fn func2(func: Function, left: Expr, right: Expr) -> Expr{
+            Expr::Func2(func, Box::new(left), Box::new(right))
+        }
+
+        pub fn agg(agg: Aggregate, var: &str, body: Expr, from: Expr, to: Expr) -> Expr{
+            Expr::Agg(agg, var.to_string(), Box::new(body), Box::new(from), Box::new(to))
+        }
+
+        // Occurrences of name bound by an aggregate inside the expression don't count.
+        pub fn contains_var(&self, name: &str) -> bool{
+            match self{
+                Expr::Num(_) => false,
+                Expr::Var(var) => var == name,
+                Expr::Op(_, left, right) => left.contains_var(name) || right.contains_var(name),
+                Expr::Func(_, arg) => arg.contains_var(name),
+                Expr::Func2(_, left, right) => left.contains_var(name) || right.contains_var(name),
+                Expr::Agg(_, var, body, from, to) => {
+                    from.contains_var(name) || to.contains_var(name) || (var != name && body.contains_var(name))
+                }
+            }
+        }
+    }
+
+    // Algebraic mode takes variables to be finite and -0 to be the same as 0, the strict mode keeps IEEE results bit for bit.
+
+    // Whether the subtree can never evaluate to NaN or an infinity.
+    fn is_finite(expr: &Expr, algebraic: bool) -> bool{
+        match expr{
+            Expr::Num(num) => num.is_finite(),
+            Expr::Var(_) => algebraic,
+            Expr::Func(Function::Sin | Function::Cos | Function::Neg, arg) => is_finite(arg, algebraic),
+            Expr::Op(Operator::Plus | Operator::Minus | Operator::Mult, left, right) if algebraic => {
+                is_finite(left, algebraic) && is_finite(right, algebraic)
+            },
+            _ => false
+        }
+    }
+
+    // A zero with the given sign, either sign in algebraic mode.
+    fn is_zero(expr: &Expr, negative: bool, algebraic: bool) -> bool{
+        matches!(expr, Expr::Num(num) if *num == 0f64 && (algebraic || num.is_sign_negative() == negative))
+    }
+
+    fn rewrite(expr: Expr, algebraic: bool) -> Expr{
+        match expr{
+            Expr::Op(op, left, right) => {
+                match (op, *left, *right){
+                    (op, Expr::Num(l), Expr::Num(r)) => Expr::Num(op.evaluate(l, r)),
+                    // x + -0 and x - 0 are x for every x, x + 0 is not for x = -0
+                    (Operator::Plus, x, zero) | (Operator::Plus, zero, x) if is_zero(&zero, true, algebraic) => x,
+                    (Operator::Minus, x, zero) if is_zero(&zero, false, algebraic) => x,
+                    // -0 - x is -x, 0 - x is not for x = 0
+                    (Operator::Minus, zero, x) if is_zero(&zero, true, algebraic) => rewrite(Expr::func(Function::Neg, x), algebraic),
+                    (Operator::Plus, x, Expr::Func(Function::Neg, y)) => rewrite(Expr::Op(Operator::Minus, Box::new(x), y), algebraic),
+                    (Operator::Minus, x, Expr::Func(Function::Neg, y)) => rewrite(Expr::Op(Operator::Plus, Box::new(x), y), algebraic),
+                    (Operator::Mult, x, Expr::Num(1f64)) | (Operator::Mult, Expr::Num(1f64), x) => x,
+                    // 0 * inf and 0 * NaN are NaN, so x * 0 only folds when x is known to be finite,
+                    // and only in algebraic mode since the sign of the zero follows x
+                    (Operator::Mult, x, zero) | (Operator::Mult, zero, x) if algebraic && is_zero(&zero, false, algebraic) && is_finite(&x, algebraic) => {
+                        Expr::Num(0f64)
+                    },
+                    (Operator::Div, x, Expr::Num(1f64)) => x,
+                    (Operator::Pow, x, Expr::Num(1f64)) => x,
+                    // powf(x, 0) and powf(1, x) are 1 even for NaN and infinities
+                    (Operator::Pow, _, Expr::Num(0f64)) => Expr::Num(1f64),
+                    (Operator::Pow, Expr::Num(1f64), _) => Expr::Num(1f64),
+                    (op, left, right) => Expr::op(op, left, right)
+                }
+            },
+            Expr::Func(func, arg) => {
+                match (func, *arg){
+                    (func, Expr::Num(num)) => Expr::Num(func.evaluate(num)),
+                    (Function::Neg, Expr::Func(Function::Neg, x)) => *x,
+                    (func, arg) => Expr::func(func, arg)
+                }
+            },
+            Expr::Func2(func, left, right) => {
+                match (*left, *right){
+                    (Expr::Num(l), Expr::Num(r)) => Expr::Num(func.evaluate_binary(l, r)),
+                    (left, right) => Expr::func2(func, left, right)
+                }
+            },
+            expr => expr
+        }
+    }
+
+    pub fn simplify_expr(expr: Expr, algebraic: bool) -> Expr{
+        match expr{
+            Expr::Op(op, left, right) => {
+                let left = simplify_expr(*left, algebraic);
+                let right = simplify_expr(*right, algebraic);
+                rewrite(Expr::op(op, left, right), algebraic)
+            },
+            Expr::Func(func, arg) => {
+                let arg = simplify_expr(*arg, algebraic);
+                rewrite(Expr::func(func, arg), algebraic)
+            },
+            Expr::Func2(func, left, right) => {
+                let left = simplify_expr(*left, algebraic);
+                let right = simplify_expr(*right, algebraic);
+                rewrite(Expr::func2(func, left, right), algebraic)
+            },
+            Expr::Agg(agg, var, body, from, to) => {
+                let body = simplify_expr(*body, algebraic);
+                let from = simplify_expr(*from, algebraic);
+                let to = simplify_expr(*to, algebraic);
+                // Only finite bounds are sure to evaluate, others are a BadRange
+                match (agg, body){
+                    (Aggregate::Integrate | Aggregate::Sum, Expr::Num(0f64)) if is_finite(&from, algebraic) && is_finite(&to, algebraic) => {
+                        Expr::Num(0f64)
+                    },
+                    (Aggregate::Product, Expr::Num(1f64)) if is_finite(&from, algebraic) && is_finite(&to, algebraic) => {
+                        Expr::Num(1f64)
+                    },
+                    (agg, body) => Expr::agg(agg, &var, body, from, to)
+                }
+            },
+            expr => expr
+        }
+    }
+
+    impl Tree<Value>{
+        pub fn simplify(&self) -> Result<Tree<Value>, EvalError>{
+            Ok(simplify_expr(Expr::from_tree(self)?, false).to_tree())
+        }
+
+        // Also folds x * 0 and x + 0, treating variables as finite and -0 as 0.
+        pub fn simplify_algebraic(&self) -> Result<Tree<Value>, EvalError>{
+            Ok(simplify_expr(Expr::from_tree(self)?, true).to_tree())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use crate::calculator_model::model::parse_to_tree;
+
+        fn strict(src: &str) -> String{
+            parse_to_tree(src).unwrap().simplify().unwrap().to_string()
+        }
+
+        fn algebraic(src: &str) -> String{
+            parse_to_tree(src).unwrap().simplify_algebraic().unwrap().to_string()
+        }
+
+        #[test]
+        fn folds_constants(){
+            assert_eq!(strict("2 * 3 + x"), "6 + x");
+            assert_eq!(strict("sin(0) + cos(0)"), "1");
+            assert_eq!(strict("x ^ (4 - 3)"), "x");
+            assert_eq!(strict("x ^ 0"), "1");
+        }
+
+        #[test]
+        fn strict_mode_keeps_the_sign_of_zero(){
+            assert_eq!(strict("x + 0"), "x + 0");
+            assert_eq!(strict("0 - x"), "0 - x");
+            assert_eq!(strict("x * 0"), "x * 0");
+            assert_eq!(strict("x - 0"), "x");
+            assert_eq!(strict("x + -0"), "x");
+            assert_eq!(strict("-0 - x"), "-x");
+        }
+
+        #[test]
+        fn algebraic_mode_drops_zeros(){
+            assert_eq!(algebraic("x + 0"), "x");
+            assert_eq!(algebraic("0 - x"), "-x");
+            assert_eq!(algebraic("x * 0 + y"), "y");
+            assert_eq!(algebraic("x - -y"), "x + y");
+            // The product may still be infinite
+            assert_eq!(algebraic("x * 0 + 1 / x * 0"), "1 / x * 0");
+        }
+
+        #[test]
+        fn every_rule_keeps_the_value(){
+            let env = [("x".to_string(), -0f64), ("y".to_string(), 2f64)].into_iter().collect();
+            for src in ["x + 0", "0 - x", "x - 0", "x + -0", "-0 - x", "x * 0", "x * 1 - -y"]{
+                let mut before = parse_to_tree(src).unwrap();
+                let mut after = before.simplify().unwrap();
+                let (a, b) = (before.rec_evaluate_env(&env).unwrap(), after.rec_evaluate_env(&env).unwrap());
+                assert!(a == b && a.is_sign_negative() == b.is_sign_negative(), "{}", src);
+            }
+        }
+    }
+}
@@ -0,0 +1,339 @@
+#[allow(dead_code)]
+pub mod solve{
+    use std::fmt;
+    use crate::calculator_bytecode::bytecode::{
+        compile,
+        Program
+    };
+    use crate::calculator_derive::derive::derivative;
+    use crate::calculator_model::model::{
+        parse_number,
+        parse_to_tree,
+        Env,
+        EvalError,
+        Node,
+        Operator,
+        Tree,
+        Value
+    };
+
+    const MAX_ITERATIONS: usize = 100;
+    const TOLERANCE: f64 = 1e-12;
+    // Largest |f| at a root, relative to |f| at the guess.
+    const RESIDUAL: f64 = 1e-6;
+    // The bracket search doubles its step up to 2^40 away from the guess.
+    const MAX_DOUBLINGS: i32 = 40;
+
+    #[derive(Debug)]
+    pub enum SolveError{
+        Eval(EvalError),
+        NoConvergence
+    }
+
+    impl fmt::Display for SolveError{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            match self{
+                SolveError::Eval(e) => write!(f, "{}", e),
+                SolveError::NoConvergence => write!(f, "NoConvergence")
+            }
+        }
+    }
+
+    impl From<EvalError> for SolveError{
+        fn from(e: EvalError) -> Self{
+            SolveError::Eval(e)
+        }
+    }
+
+    // A function of the unknown with every other variable bound from the environment.
+    enum Objective{
+        Compiled{program: Program, vars: Vec<f64>, slot: Option<usize>},
+        // Aggregates do not compile to bytecode, those trees are walked instead.
+        Walked{tree: Tree<Value>, env: Env, var: String}
+    }
+
+    impl Objective{
+        fn new(tree: &Tree<Value>, var: &str, env: &Env) -> Result<Self, EvalError>{
+            let program = match compile(tree){
+                Ok(program) => program,
+                Err(EvalError::UnexpectedOp) => {
+                    let root = tree.root_node().ok_or(EvalError::BadTree)?;
+                    return Ok(Objective::Walked{tree: Tree::from_root(root.deep_copy()), env: env.clone(), var: var.to_string()});
+                },
+                Err(e) => return Err(e)
+            };
+            let mut vars = Vec::new();
+            for name in program.variables(){
+                if name == var{
+                    vars.push(0f64);
+                    continue;
+                }
+                match env.get(name){
+                    Some(num) => vars.push(*num),
+                    None => return Err(EvalError::UnknownVariable(name.clone()))
+                }
+            }
+            let slot = program.slot_of(var);
+            Ok(Objective::Compiled{program, vars, slot})
+        }
+
+        fn eval(&mut self, x: f64) -> Result<f64, EvalError>{
+            match self{
+                Objective::Compiled{program, vars, slot} => {
+                    if let Some(slot) = slot{
+                        vars[*slot] = x;
+                    }
+                    program.run(vars)
+                },
+                Objective::Walked{tree, env, var} => {
+                    env.insert(var.clone(), x);
+                    tree.evaluate_number(env)
+                }
+            }
+        }
+    }
+
+    struct Equation{
+        f: Objective,
+        df: Option<Objective>,
+        // |f| at the guess, the only scale the equation gives for how small a residual is.
+        scale: f64
+    }
+
+    impl Equation{
+        fn slope(&mut self, x: f64) -> Result<f64, EvalError>{
+            if let Some(df) = &mut self.df{
+                let d = df.eval(x)?;
+                if d.is_finite(){
+                    return Ok(d);
+                }
+            }
+            let h = 1e-7 * (1f64 + x.abs());
+            Ok((self.f.eval(x + h)? - self.f.eval(x - h)?) / (2f64 * h))
+        }
+
+        fn converged(step: f64, x: f64) -> bool{
+            step.abs() <= TOLERANCE * (1f64 + x.abs())
+        }
+
+        // Compares signs directly, the product of two tiny values can underflow to 0.
+        fn straddles(a: f64, b: f64) -> bool{
+            a.is_finite() && b.is_finite() && (a == 0f64 || b == 0f64 || (a < 0f64) != (b < 0f64))
+        }
+
+        // A sign change across a pole, or a value that underflowed to 0 on a flat tail, is not a root.
+        fn check_root(&mut self, x: f64) -> Result<f64, SolveError>{
+            let fx = self.f.eval(x)?;
+            if !fx.is_finite() || fx.abs() > RESIDUAL * (1f64 + self.scale){
+                return Err(SolveError::NoConvergence);
+            }
+            let h = 1e-7 * (1f64 + x.abs());
+            if fx == 0f64 && matches!(self.f.eval(x - h), Ok(0f64)) && matches!(self.f.eval(x + h), Ok(0f64)){
+                return Err(SolveError::NoConvergence);
+            }
+            Ok(x)
+        }
+
+        fn find_bracket(&mut self, guess: f64) -> Result<Option<(f64, f64, f64, f64)>, EvalError>{
+            let f0 = self.f.eval(guess)?;
+            if f0 == 0f64{
+                return Ok(Some((guess, f0, guess, f0)));
+            }
+            let (mut left, mut f_left) = (guess, f0);
+            let (mut right, mut f_right) = (guess, f0);
+            for k in 0..=MAX_DOUBLINGS{
+                let d = 2f64.powi(k);
+                let (r, fr) = (guess + d, self.f.eval(guess + d)?);
+                if Equation::straddles(f_right, fr){
+                    return Ok(Some((right, f_right, r, fr)));
+                }
+                let (l, fl) = (guess - d, self.f.eval(guess - d)?);
+                if Equation::straddles(f_left, fl){
+                    return Ok(Some((l, fl, left, f_left)));
+                }
+                (right, f_right, left, f_left) = (r, fr, l, fl);
+            }
+            Ok(None)
+        }
+
+        // Newton steps that fall outside the bracket are replaced by bisection.
+        fn bracketed_newton(&mut self, mut a: f64, mut fa: f64, mut b: f64, guess: f64) -> Result<f64, SolveError>{
+            if fa == 0f64{
+                return Ok(a);
+            }
+            if self.f.eval(b)? == 0f64{
+                return Ok(b);
+            }
+            let mut x = if guess > a && guess < b {guess} else {(a + b) / 2f64};
+            for _ in 0..MAX_ITERATIONS{
+                let fx = self.f.eval(x)?;
+                if fx == 0f64{
+                    return Ok(x);
+                }
+                if (fx < 0f64) == (fa < 0f64){
+                    (a, fa) = (x, fx);
+                }else{
+                    b = x;
+                }
+                let d = self.slope(x)?;
+                let newton = x - fx / d;
+                let next = if d != 0f64 && newton >= a.min(b) && newton <= a.max(b){
+                    newton
+                }else{
+                    (a + b) / 2f64
+                };
+                if Equation::converged(next - x, x) || Equation::converged(b - a, x){
+                    return Ok(next);
+                }
+                x = next;
+            }
+            Err(SolveError::NoConvergence)
+        }
+
+        fn newton(&mut self, mut x: f64) -> Result<f64, SolveError>{
+            for _ in 0..MAX_ITERATIONS{
+                let fx = self.f.eval(x)?;
+                if fx == 0f64{
+                    return Ok(x);
+                }
+                let d = self.slope(x)?;
+                let step = fx / d;
+                if !step.is_finite(){
+                    break;
+                }
+                x -= step;
+                if Equation::converged(step, x){
+                    return Ok(x);
+                }
+            }
+            Err(SolveError::NoConvergence)
+        }
+    }
+
+    // Finds x with f(x) = 0, starting the search from guess.
+    pub fn solve(tree: &Tree<Value>, var: &str, guess: f64, env: &Env) -> Result<f64, SolveError>{
+        let f = Objective::new(tree, var, env)?;
+        let df = match derivative(tree, var){
+            Ok(d) => Objective::new(&d, var, env).ok(),
+            Err(_) => None
+        };
+        let mut equation = Equation{f, df, scale: 0f64};
+        let scale = equation.f.eval(guess)?.abs();
+        if scale.is_finite(){
+            equation.scale = scale;
+        }
+        let root = match equation.find_bracket(guess)?{
+            Some((a, fa, b, _)) => equation.bracketed_newton(a, fa, b, guess)?,
+            None => equation.newton(guess)?
+        };
+        equation.check_root(root)
+    }
+
+    // Turns "lhs = rhs" into the tree lhs - rhs, a plain expression is solved for 0.
+    pub fn parse_equation(src: &str) -> Result<Tree<Value>, &'static str>{
+        let sides: Vec<&str> = src.split('=').collect();
+        match sides.as_slice(){
+            [expr] => parse_to_tree(expr),
+            [lhs, rhs] => {
+                let lhs = parse_to_tree(lhs)?;
+                let rhs = parse_to_tree(rhs)?;
+                match (lhs.root_node(), rhs.root_node()){
+                    (Some(l), Some(r)) => {
+                        let root = Node::with_children(Value::Operator(Operator::Minus), Some(l.deep_copy()), Some(r.deep_copy()));
+                        Ok(Tree::from_root(root))
+                    },
+                    _ => Err("Empty expression")
+                }
+            },
+            _ => Err("Expected a single '='")
+        }
+    }
+
+    fn split_args(src: &str) -> Vec<&str>{
+        let mut args = Vec::new();
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in src.char_indices(){
+            match c{
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    args.push(&src[start..i]);
+                    start = i + 1;
+                },
+                _ => ()
+            }
+        }
+        args.push(&src[start..]);
+        args
+    }
+
+    // Accepts "solve(lhs = rhs, x)" or "solve(lhs = rhs, x, guess)", returns the equation, unknown and guess.
+    pub fn parse_solve(src: &str) -> Result<(Tree<Value>, String, f64), &'static str>{
+        let inner = match src.trim().strip_prefix("solve"){
+            Some(rest) => {
+                match rest.trim_start().strip_prefix('(').and_then(|r| r.strip_suffix(')')){
+                    Some(inner) => inner,
+                    None => return Err("Expected solve(equation, variable)")
+                }
+            },
+            None => return Err("Expected solve(equation, variable)")
+        };
+        let args = split_args(inner);
+        let (equation, var, guess) = match args.as_slice(){
+            [equation, var] => (equation, var.trim(), 0f64),
+            [equation, var, guess] => (equation, var.trim(), parse_number(guess.trim())?),
+            _ => return Err("Expected solve(equation, variable)")
+        };
+        if var.is_empty() || !var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'){
+            return Err("Bad variable name");
+        }
+        Ok((parse_equation(equation)?, var.to_string(), guess))
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+
+        fn root(src: &str) -> Result<f64, SolveError>{
+            let (tree, var, guess) = parse_solve(src).unwrap();
+            solve(&tree, &var, guess, &Env::new())
+        }
+
+        #[test]
+        fn finds_roots_from_the_guess(){
+            assert!((root("solve(x^2 = 2, x, 1)").unwrap() - 2f64.sqrt()).abs() < 1e-12);
+            assert!((root("solve(x^2 = 2, x, -1)").unwrap() + 2f64.sqrt()).abs() < 1e-12);
+            assert!((root("solve(exp(y) = 5, y)").unwrap() - 5f64.ln()).abs() < 1e-12);
+            assert_eq!(root("solve(x^3, x, 1)").unwrap(), 0f64);
+        }
+
+        #[test]
+        fn binds_other_variables_from_the_environment(){
+            let (tree, var, guess) = parse_solve("solve(x * k = 6, x)").unwrap();
+            let env = Env::from([("k".to_string(), 3f64)]);
+            assert_eq!(solve(&tree, &var, guess, &env).unwrap(), 2f64);
+            assert!(matches!(solve(&tree, &var, guess, &Env::new()), Err(SolveError::Eval(EvalError::UnknownVariable(_)))));
+        }
+
+        #[test]
+        fn poles_and_underflow_are_not_roots(){
+            assert!(matches!(root("solve(1/(x-0.5) = 0, x)"), Err(SolveError::NoConvergence)));
+            assert!(matches!(root("solve(exp(x) = 0, x)"), Err(SolveError::NoConvergence)));
+            assert!(matches!(root("solve(x^2 = -1, x)"), Err(SolveError::NoConvergence)));
+        }
+
+        #[test]
+        fn aggregates_fall_back_to_the_tree(){
+            assert_eq!(root("solve(sum(i*x, i, 1, 3) = 6, x)").unwrap(), 1f64);
+        }
+
+        #[test]
+        fn malformed_calls(){
+            assert!(matches!(parse_solve("x = 1"), Err("Expected solve(equation, variable)")));
+            assert!(matches!(parse_solve("solve(x = 1)"), Err("Expected solve(equation, variable)")));
+            assert!(matches!(parse_solve("solve(x = 1 = 2, x)"), Err("Expected a single '='")));
+            assert!(matches!(parse_solve("solve(x = 1, 2 x)"), Err("Bad variable name")));
+        }
+    }
+}
@@ -0,0 +1,226 @@
+#[allow(dead_code)]
+pub mod uncertain{
+    use std::{
+        collections::BTreeMap,
+        fmt,
+        sync::atomic::{
+            AtomicUsize,
+            Ordering
+        }
+    };
+    use crate::calculator_model::model::{
+        parse_number,
+        parse_to_tree_with_literals,
+        EvalError,
+        Function,
+        Literals,
+        Tree,
+        Value
+    };
+    use crate::calculator_number::number::Number;
+
+    // Every measured literal is an independent source of error.
+    static NEXT_SOURCE: AtomicUsize = AtomicUsize::new(0);
+
+    // Value with its linearised error, kept per source so that x - x comes out exact.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Uncertain{
+        pub value: f64,
+        terms: BTreeMap<usize, f64>
+    }
+
+    impl Uncertain{
+        pub fn exact(value: f64) -> Uncertain{
+            Uncertain{value, terms: BTreeMap::new()}
+        }
+
+        // A fresh independent measurement with standard uncertainty sigma.
+        pub fn measured(value: f64, sigma: f64) -> Uncertain{
+            let mut terms = BTreeMap::new();
+            if sigma != 0f64{
+                terms.insert(NEXT_SOURCE.fetch_add(1, Ordering::Relaxed), sigma);
+            }
+            Uncertain{value, terms}
+        }
+
+        // "9.81±0.02" or a plain exact number.
+        pub fn parse_literal(src: &str) -> Result<Uncertain, &'static str>{
+            match src.split_once('±'){
+                Some((value, sigma)) => Ok(Uncertain::measured(parse_number(value)?, parse_number(sigma)?)),
+                None => Ok(Uncertain::exact(parse_number(src)?))
+            }
+        }
+
+        // Independent contributions add in quadrature.
+        pub fn sigma(&self) -> f64{
+            self.terms.values().fold(0f64, |acc, t| acc + t * t).sqrt()
+        }
+
+        // f(a) with f'(a) = da.
+        fn chain(&self, value: f64, da: f64) -> Uncertain{
+            Uncertain{value, terms: self.terms.iter().map(|(&id, t)| (id, t * da)).collect()}
+        }
+
+        // f(a, b) with partial derivatives da and db.
+        fn combine(value: f64, a: &Uncertain, da: f64, b: &Uncertain, db: f64) -> Uncertain{
+            let mut terms = a.chain(value, da).terms;
+            for (&id, t) in &b.terms{
+                *terms.entry(id).or_insert(0f64) += t * db;
+            }
+            Uncertain{value, terms}
+        }
+
+        pub fn add(&self, other: &Uncertain) -> Uncertain{
+            Uncertain::combine(self.value + other.value, self, 1f64, other, 1f64)
+        }
+
+        pub fn sub(&self, other: &Uncertain) -> Uncertain{
+            Uncertain::combine(self.value - other.value, self, 1f64, other, -1f64)
+        }
+
+        pub fn mul(&self, other: &Uncertain) -> Uncertain{
+            Uncertain::combine(self.value * other.value, self, other.value, other, self.value)
+        }
+
+        pub fn div(&self, other: &Uncertain) -> Uncertain{
+            let value = self.value / other.value;
+            Uncertain::combine(value, self, 1f64 / other.value, other, -value / other.value)
+        }
+
+        // ln of the base only matters, and is only finite, when the exponent carries an error.
+        pub fn pow(&self, other: &Uncertain) -> Uncertain{
+            let value = self.value.powf(other.value);
+            let da = other.value * self.value.powf(other.value - 1f64);
+            Uncertain::combine(value, self, da, other, value * self.value.ln())
+        }
+
+        pub fn evaluate(&self, func: Function) -> Uncertain{
+            let x = self.value;
+            let value = func.evaluate(x);
+            let slope = match func{
+                Function::Sin => x.cos(),
+                Function::Cos => -x.sin(),
+                Function::Tan => 1f64 / (x.cos() * x.cos()),
+                Function::Exp => value,
+                Function::Ln => 1f64 / x,
+                Function::Sqrt => 0.5f64 / value,
+                Function::Abs => x.signum(),
+                Function::Neg => -1f64,
+                Function::Re | Function::Conj => 1f64,
+                Function::Im | Function::Arg => 0f64,
+                // Defined only at integers, so there is no slope to carry an error.
+                Function::Fact | Function::Binom | Function::Not | Function::Popcount | Function::Rotl | Function::Rotr => f64::NAN
+            };
+            self.chain(value, slope)
+        }
+    }
+
+    impl fmt::Display for Uncertain{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            write!(f, "{} ± {}", self.value, self.sigma())
+        }
+    }
+
+    impl Number for Uncertain{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            Uncertain::parse_literal(src)
+        }
+
+        fn zero() -> Self{
+            Uncertain::exact(0f64)
+        }
+
+        fn one() -> Self{
+            Uncertain::exact(1f64)
+        }
+
+        fn to_f64(&self) -> f64{
+            self.value
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Uncertain::add(self, other))
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Uncertain::sub(self, other))
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Uncertain::mul(self, other))
+        }
+
+        // The slope of 1/x has no value at zero, so neither does the error.
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            if other.value == 0f64{
+                return Err(EvalError::DivisionByZero);
+            }
+            Ok(Uncertain::div(self, other))
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Uncertain::pow(self, other))
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            Ok(self.evaluate(func))
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            let value = func.evaluate_binary(self.value, other.value);
+            Ok(Uncertain::combine(value, self, f64::NAN, other, f64::NAN))
+        }
+    }
+
+    // x +- dx reads the same as x ± dx.
+    pub fn parse_uncertain(src: &str) -> Result<Tree<Value<Uncertain>>, &'static str>{
+        parse_to_tree_with_literals(src, Literals{plus_minus: true, ..Literals::default()}, Uncertain::parse_literal)
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use std::collections::HashMap;
+        use super::*;
+
+        fn measure(src: &str) -> Uncertain{
+            parse_uncertain(src).unwrap().evaluate_number(&HashMap::new()).unwrap()
+        }
+
+        #[test]
+        fn independent_errors_add_in_quadrature(){
+            let sum = measure("10 ± 3 + 20 ± 4");
+            assert_eq!((sum.value, sum.sigma()), (30f64, 5f64));
+            assert_eq!(measure("3 * 2").sigma(), 0f64);
+            assert_eq!(measure("3 ± 0.5 * 2").sigma(), 1f64);
+        }
+
+        #[test]
+        fn shared_sources_are_correlated(){
+            let env = HashMap::from([("x".to_string(), Uncertain::measured(2f64, 0.1))]);
+            let at = |src: &str| parse_uncertain(src).unwrap().evaluate_number(&env).unwrap();
+            assert_eq!(at("x - x").sigma(), 0f64);
+            assert_eq!((at("x / x").value, at("x / x").sigma()), (1f64, 0f64));
+            assert!((at("x * x").sigma() - 0.4).abs() < 1e-12);
+            assert!((at("x * 2 ± 0.2").sigma() - 0.2f64.hypot(0.4)).abs() < 1e-12);
+        }
+
+        #[test]
+        fn functions_scale_by_their_slope(){
+            assert!((measure("ln(2 ± 0.2)").sigma() - 0.1).abs() < 1e-12);
+            assert!((measure("sqrt(4 ± 0.4)").sigma() - 0.1).abs() < 1e-12);
+            assert!(measure("(3 ± 0.1)!").sigma().is_nan());
+            assert_eq!(measure("3!").sigma(), 0f64);
+            let error = |src: &str| parse_uncertain(src).unwrap().evaluate_number(&HashMap::new()).err();
+            assert!(matches!(error("(1 ± 0.1) / 0"), Some(EvalError::DivisionByZero)));
+            assert!(matches!(error("1 / (2 ± 0.1 - 2)"), Some(EvalError::DivisionByZero)));
+        }
+
+        #[test]
+        fn ascii_plus_minus_reads_as_uncertainty(){
+            assert_eq!(measure("9.81 +- 0.02").to_string(), "9.81 ± 0.02");
+            let glued = measure("1+-2");
+            assert_eq!((glued.value, glued.sigma()), (1f64, 2f64));
+            assert!(Uncertain::parse_literal("1±x").is_err());
+        }
+    }
+}
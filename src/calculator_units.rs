@@ -0,0 +1,413 @@
+#[allow(dead_code)]
+pub mod units{
+    use std::{
+        collections::HashMap,
+        f64::consts::PI,
+        fmt
+    };
+    use crate::calculator_model::model::{
+        parse_number,
+        parse_to_tree_with,
+        Aggregate,
+        EvalError,
+        Function,
+        Node,
+        Tree,
+        Value
+    };
+    use crate::calculator_number::number::Number;
+    use crate::calculator_currency::currency::{
+        is_currency_code,
+        RateTable
+    };
+    use crate::calculator_format::format::Format;
+
+    // Exponents of the SI base units and money, in the order they are printed.
+    pub type Dims = [i32; 8];
+    // Money prints in the base currency of the rate table, XXX when there is none.
+    pub const BASE_UNITS: [&str; 8] = ["kg", "m", "s", "A", "K", "mol", "cd", "XXX"];
+    const DIMENSIONLESS: Dims = [0; 8];
+    const MONEY: Dims = [0, 0, 0, 0, 0, 0, 0, 1];
+    // Longer prefixes first so "da" wins over "d".
+    const PREFIXES: [(&str, f64); 20] = [
+        ("da", 1e1), ("Y", 1e24), ("Z", 1e21), ("E", 1e18), ("P", 1e15), ("T", 1e12), ("G", 1e9), ("M", 1e6), ("k", 1e3), ("h", 1e2),
+        ("d", 1e-1), ("c", 1e-2), ("m", 1e-3), ("u", 1e-6), ("n", 1e-9), ("p", 1e-12), ("f", 1e-15), ("a", 1e-18), ("z", 1e-21), ("y", 1e-24)
+    ];
+    // Name, SI value, exponents of kg m s A K mol cd, whether SI prefixes apply.
+    const BUILTIN: [(&str, f64, Dims, bool); 44] = [
+        ("m", 1f64, [0, 1, 0, 0, 0, 0, 0, 0], true),
+        ("g", 1e-3, [1, 0, 0, 0, 0, 0, 0, 0], true),
+        ("s", 1f64, [0, 0, 1, 0, 0, 0, 0, 0], true),
+        ("A", 1f64, [0, 0, 0, 1, 0, 0, 0, 0], true),
+        ("K", 1f64, [0, 0, 0, 0, 1, 0, 0, 0], true),
+        ("mol", 1f64, [0, 0, 0, 0, 0, 1, 0, 0], true),
+        ("cd", 1f64, [0, 0, 0, 0, 0, 0, 1, 0], true),
+        ("Hz", 1f64, [0, 0, -1, 0, 0, 0, 0, 0], true),
+        ("N", 1f64, [1, 1, -2, 0, 0, 0, 0, 0], true),
+        ("Pa", 1f64, [1, -1, -2, 0, 0, 0, 0, 0], true),
+        ("J", 1f64, [1, 2, -2, 0, 0, 0, 0, 0], true),
+        ("W", 1f64, [1, 2, -3, 0, 0, 0, 0, 0], true),
+        ("C", 1f64, [0, 0, 1, 1, 0, 0, 0, 0], true),
+        ("V", 1f64, [1, 2, -3, -1, 0, 0, 0, 0], true),
+        ("ohm", 1f64, [1, 2, -3, -2, 0, 0, 0, 0], true),
+        ("S", 1f64, [-1, -2, 3, 2, 0, 0, 0, 0], true),
+        ("F", 1f64, [-1, -2, 4, 2, 0, 0, 0, 0], true),
+        ("Wb", 1f64, [1, 2, -2, -1, 0, 0, 0, 0], true),
+        ("T", 1f64, [1, 0, -2, -1, 0, 0, 0, 0], true),
+        ("H", 1f64, [1, 2, -2, -2, 0, 0, 0, 0], true),
+        ("L", 1e-3, [0, 3, 0, 0, 0, 0, 0, 0], true),
+        ("l", 1e-3, [0, 3, 0, 0, 0, 0, 0, 0], true),
+        ("eV", 1.602176634e-19, [1, 2, -2, 0, 0, 0, 0, 0], true),
+        ("Wh", 3600f64, [1, 2, -2, 0, 0, 0, 0, 0], true),
+        ("cal", 4.184, [1, 2, -2, 0, 0, 0, 0, 0], true),
+        ("bar", 1e5, [1, -1, -2, 0, 0, 0, 0, 0], true),
+        ("min", 60f64, [0, 0, 1, 0, 0, 0, 0, 0], false),
+        ("h", 3600f64, [0, 0, 1, 0, 0, 0, 0, 0], false),
+        ("day", 86400f64, [0, 0, 1, 0, 0, 0, 0, 0], false),
+        ("week", 604800f64, [0, 0, 1, 0, 0, 0, 0, 0], false),
+        ("inch", 0.0254, [0, 1, 0, 0, 0, 0, 0, 0], false),
+        ("ft", 0.3048, [0, 1, 0, 0, 0, 0, 0, 0], false),
+        ("yd", 0.9144, [0, 1, 0, 0, 0, 0, 0, 0], false),
+        ("mi", 1609.344, [0, 1, 0, 0, 0, 0, 0, 0], false),
+        ("nmi", 1852f64, [0, 1, 0, 0, 0, 0, 0, 0], false),
+        ("mph", 0.44704, [0, 1, -1, 0, 0, 0, 0, 0], false),
+        ("kn", 1852f64 / 3600f64, [0, 1, -1, 0, 0, 0, 0, 0], false),
+        ("lb", 0.45359237, [1, 0, 0, 0, 0, 0, 0, 0], false),
+        ("lbf", 4.4482216152605, [1, 1, -2, 0, 0, 0, 0, 0], false),
+        ("psi", 6894.757293168361, [1, -1, -2, 0, 0, 0, 0, 0], false),
+        ("atm", 101325f64, [1, -1, -2, 0, 0, 0, 0, 0], false),
+        ("hp", 745.6998715822702, [1, 2, -3, 0, 0, 0, 0, 0], false),
+        ("rad", 1f64, DIMENSIONLESS, false),
+        ("deg", PI / 180f64, DIMENSIONLESS, false)
+    ];
+    // How close d * p must come to a whole number for a power of a unit to make sense.
+    const EXPONENT_TOLERANCE: f64 = 1e-9;
+
+    // A value in SI base units.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Quantity{
+        pub value: f64,
+        pub dims: Dims
+    }
+
+    impl Quantity{
+        pub fn new(value: f64, dims: Dims) -> Quantity{
+            Quantity{value, dims}
+        }
+
+        pub fn scalar(value: f64) -> Quantity{
+            Quantity{value, dims: DIMENSIONLESS}
+        }
+
+        pub fn is_dimensionless(&self) -> bool{
+            self.dims == DIMENSIONLESS
+        }
+
+        fn scalar_only(&self) -> Result<f64, EvalError>{
+            if self.is_dimensionless() {Ok(self.value)} else {Err(EvalError::DimensionMismatch)}
+        }
+
+        // Same dimension on both sides, a bare zero fits any of them.
+        fn matched(&self, other: &Quantity) -> Result<Dims, EvalError>{
+            match (self.dims == other.dims, self.value == 0f64 && self.is_dimensionless(), other.value == 0f64 && other.is_dimensionless()){
+                (true, _, _) | (_, _, true) => Ok(self.dims),
+                (_, true, _) => Ok(other.dims),
+                _ => Err(EvalError::DimensionMismatch)
+            }
+        }
+
+        fn scaled_dims(&self, power: f64) -> Result<Dims, EvalError>{
+            let mut dims = DIMENSIONLESS;
+            for (out, d) in dims.iter_mut().zip(self.dims){
+                let scaled = d as f64 * power;
+                if (scaled - scaled.round()).abs() > EXPONENT_TOLERANCE{
+                    return Err(EvalError::DimensionMismatch);
+                }
+                if scaled.round() < i32::MIN as f64 || scaled.round() > i32::MAX as f64{
+                    return Err(EvalError::Overflow);
+                }
+                *out = scaled.round() as i32;
+            }
+            Ok(dims)
+        }
+
+        // Exponents of a product or quotient, side by side.
+        fn combined_dims(&self, other: &Quantity, op: fn(i32, i32) -> Option<i32>) -> Result<Dims, EvalError>{
+            let mut dims = DIMENSIONLESS;
+            for ((out, d), o) in dims.iter_mut().zip(self.dims).zip(other.dims){
+                *out = op(d, o).ok_or(EvalError::Overflow)?;
+            }
+            Ok(dims)
+        }
+    }
+
+    // Base units as an expression that reads back, like kg*m^2/s^2.
+    pub fn unit_string(dims: &Dims, currency: &str) -> String{
+        let mut names = BASE_UNITS;
+        names[7] = currency;
+        let part = |name: &str, exp: i32| if exp == 1 {name.to_string()} else {format!("{}^{}", name, exp)};
+        let num: Vec<String> = names.iter().zip(dims).filter(|(_, &d)| d > 0).map(|(n, &d)| part(n, d)).collect();
+        let den: Vec<String> = names.iter().zip(dims).filter(|(_, &d)| d < 0).map(|(n, &d)| part(n, -d)).collect();
+        match (num.is_empty(), den.len()){
+            (_, 0) => num.join("*"),
+            (true, _) => names.iter().zip(dims).filter(|(_, &d)| d < 0).map(|(n, &d)| part(n, d)).collect::<Vec<String>>().join("*"),
+            (false, 1) => format!("{}/{}", num.join("*"), den[0]),
+            (false, _) => format!("{}/({})", num.join("*"), den.join("*"))
+        }
+    }
+
+    impl fmt::Display for Quantity{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            if self.is_dimensionless(){
+                write!(f, "{}", self.value)
+            }else{
+                write!(f, "{} {}", self.value, unit_string(&self.dims, BASE_UNITS[7]))
+            }
+        }
+    }
+
+    impl Number for Quantity{
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            Ok(Quantity::scalar(parse_number(src)?))
+        }
+
+        fn zero() -> Self{
+            Quantity::scalar(0f64)
+        }
+
+        fn one() -> Self{
+            Quantity::scalar(1f64)
+        }
+
+        // Ranges of sums and products have to be plain numbers.
+        fn to_f64(&self) -> f64{
+            if self.is_dimensionless() {self.value} else {f64::NAN}
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Quantity::new(self.value + other.value, self.matched(other)?))
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Quantity::new(self.value - other.value, self.matched(other)?))
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            Ok(Quantity::new(self.value * other.value, self.combined_dims(other, i32::checked_add)?))
+        }
+
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            if other.value == 0f64{
+                return Err(EvalError::DivisionByZero);
+            }
+            Ok(Quantity::new(self.value / other.value, self.combined_dims(other, i32::checked_sub)?))
+        }
+
+        // The exponent is a plain number, and has to leave whole exponents on every unit.
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            let power = other.scalar_only()?;
+            Ok(Quantity::new(self.value.powf(power), self.scaled_dims(power)?))
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            match func{
+                Function::Sqrt => Ok(Quantity::new(self.value.sqrt(), self.scaled_dims(0.5)?)),
+                Function::Abs | Function::Neg | Function::Re | Function::Conj => Ok(Quantity::new(func.evaluate(self.value), self.dims)),
+                Function::Im => Ok(Quantity::new(0f64, self.dims)),
+                Function::Arg => Ok(Quantity::scalar(func.evaluate(self.value))),
+                _ => Ok(Quantity::scalar(func.evaluate(self.scalar_only()?)))
+            }
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            Ok(Quantity::scalar(func.evaluate_binary(self.scalar_only()?, other.scalar_only()?)))
+        }
+    }
+
+    fn collect_names(node: &Node<Value<Quantity>>, names: &mut Vec<String>){
+        if let Some(Value::Variable(name)) = &node.value{
+            names.push(name.clone());
+        }
+        if let Some(left) = node.left_node(){
+            collect_names(left, names);
+        }
+        if let Some(right) = node.right_node(){
+            collect_names(right, names);
+        }
+    }
+
+    pub fn parse_units(src: &str) -> Result<Tree<Value<Quantity>>, &'static str>{
+        parse_to_tree_with(src, Quantity::from_literal)
+    }
+
+    // Unit names mapped to their size in SI base units, currencies through an optional rate table.
+    pub struct UnitTable{
+        units: HashMap<String, (Quantity, bool)>,
+        rates: Option<RateTable>,
+        format: Format
+    }
+
+    impl Default for UnitTable{
+        fn default() -> Self{
+            let mut table = UnitTable::new();
+            for (name, value, dims, prefixable) in BUILTIN{
+                table.define(name, Quantity::new(value, dims), prefixable);
+            }
+            table
+        }
+    }
+
+    impl UnitTable{
+        pub fn new() -> UnitTable{
+            UnitTable{units: HashMap::new(), rates: None, format: Format::default()}
+        }
+
+        pub fn set_rates(&mut self, rates: RateTable){
+            self.rates = Some(rates);
+        }
+
+        // How evaluate_str prints the number in front of the unit.
+        pub fn set_format(&mut self, format: Format){
+            self.format = format;
+        }
+
+        pub fn define(&mut self, name: &str, quantity: Quantity, prefixable: bool){
+            self.units.insert(name.to_string(), (quantity, prefixable));
+        }
+
+        // "furlong=201.168 m", the right side in units already known.
+        pub fn define_str(&mut self, definition: &str) -> Result<(), String>{
+            let (name, expr) = definition.split_once('=').ok_or("Bad unit definition")?;
+            let name = name.trim();
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid || Function::from_name(name).is_some() || Aggregate::from_name(name).is_some(){
+                return Err("Bad unit name".to_string());
+            }
+            let tree = parse_units(expr)?;
+            let quantity = self.evaluate(&tree).map_err(|e| e.to_string())?;
+            self.define(name, quantity, false);
+            Ok(())
+        }
+
+        // Exact names first, then currencies, then an SI prefix on a unit that takes one.
+        pub fn lookup(&self, name: &str) -> Option<Quantity>{
+            if let Some((quantity, _)) = self.units.get(name){
+                return Some(*quantity);
+            }
+            if let Some(value) = self.rates.as_ref().and_then(|rates| rates.value_of(name)){
+                return Some(Quantity::new(value, MONEY));
+            }
+            PREFIXES.iter().find_map(|(prefix, factor)| {
+                match self.units.get(name.strip_prefix(prefix)?){
+                    Some((quantity, true)) => Some(Quantity::new(quantity.value * factor, quantity.dims)),
+                    _ => None
+                }
+            })
+        }
+
+        // Every free name is read as a unit.
+        pub fn evaluate(&self, tree: &Tree<Value<Quantity>>) -> Result<Quantity, EvalError>{
+            let mut names = Vec::new();
+            if let Some(root) = tree.root_node(){
+                collect_names(root, &mut names);
+            }
+            let mut env = HashMap::new();
+            for name in names{
+                match self.lookup(&name){
+                    Some(quantity) => {
+                        env.insert(name, quantity);
+                    },
+                    None if is_currency_code(&name) => return Err(EvalError::MissingRate(name)),
+                    None => ()
+                }
+            }
+            tree.evaluate_number(&env)
+        }
+
+        // Results in money say which rates they were converted at.
+        fn rates_note(&self, dims: &Dims) -> String{
+            match &self.rates{
+                Some(rates) if dims[7] != 0 => format!(" (rates of {})", rates.timestamp),
+                _ => String::new()
+            }
+        }
+
+        // "60 mph to km/h" prints in the target unit, anything else in SI base units.
+        pub fn evaluate_str(&self, src: &str) -> Result<String, String>{
+            let (expr, target) = match src.rsplit_once(" to "){
+                Some((expr, target)) => (expr, Some(target.trim())),
+                None => (src, None)
+            };
+            let quantity = self.evaluate(&parse_units(expr)?).map_err(|e| e.to_string())?;
+            match target{
+                Some(target) => {
+                    let unit = self.evaluate(&parse_units(target)?).map_err(|e| e.to_string())?;
+                    if unit.dims != quantity.dims{
+                        return Err(EvalError::DimensionMismatch.to_string());
+                    }
+                    Ok(format!("{} {}{}", self.format.apply(quantity.value / unit.value), target, self.rates_note(&unit.dims)))
+                },
+                None if quantity.is_dimensionless() => Ok(self.format.apply(quantity.value)),
+                None => {
+                    let currency = self.rates.as_ref().map_or(BASE_UNITS[7], |rates| rates.base.as_str());
+                    Ok(format!("{} {}{}", self.format.apply(quantity.value), unit_string(&quantity.dims, currency), self.rates_note(&quantity.dims)))
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use super::*;
+        use crate::calculator_format::format::Notation;
+
+        fn evaluate(src: &str) -> Result<String, String>{
+            UnitTable::default().evaluate_str(src)
+        }
+
+        #[test]
+        fn converts_between_compatible_units(){
+            assert_eq!(evaluate("3 km + 200 m").unwrap(), "3200 m");
+            assert_eq!(evaluate("2 kg * 3 m / s^2").unwrap(), "6 kg*m/s^2");
+            assert_eq!(evaluate("100 km / (2 h) to m/s").unwrap(), "13.88888888888889 m/s");
+        }
+
+        #[test]
+        fn bad_quantities_are_errors(){
+            assert_eq!(evaluate("3 km + 2 s").unwrap_err(), "DimensionMismatch");
+            assert_eq!(evaluate("3 km to s").unwrap_err(), "DimensionMismatch");
+            assert_eq!(evaluate("sqrt(2 m)").unwrap_err(), "DimensionMismatch");
+            assert_eq!(evaluate("sqrt(9 m^2)").unwrap(), "3 m");
+            assert_eq!(evaluate("1 m / 0").unwrap_err(), "DivisionByZero");
+            assert_eq!(evaluate("1 m / (0 s)").unwrap_err(), "DivisionByZero");
+            assert_eq!(evaluate("0 m / (1 s)").unwrap(), "0 m/s");
+        }
+
+        #[test]
+        fn prefixes_and_definitions(){
+            assert_eq!(evaluate("1 km to m").unwrap(), "1000 m");
+            let mut table = UnitTable::default();
+            table.define_str("furlong=201.168 m").unwrap();
+            assert_eq!(table.evaluate_str("1 furlong to m").unwrap(), "201.168 m");
+            assert!(table.define_str("sin=2 m").is_err());
+        }
+
+        #[test]
+        fn results_follow_the_format(){
+            let mut table = UnitTable::default();
+            table.set_format(Format{notation: Notation::Fixed, precision: 2, ..Format::default()});
+            assert_eq!(table.evaluate_str("1 m / (3 s)").unwrap(), "0.33 m/s");
+            assert_eq!(table.evaluate_str("1 km / (3 h) to m/s").unwrap(), "0.09 m/s");
+            assert_eq!(table.evaluate_str("2 / 3").unwrap(), "0.67");
+        }
+
+        #[test]
+        fn exponent_overflow(){
+            assert_eq!(evaluate("(1 m)^2000000000 * (1 m)^2000000000").unwrap_err(), "Overflow");
+            assert_eq!(evaluate("(1 m)^-2000000000 / (1 m)^2000000000").unwrap_err(), "Overflow");
+            assert_eq!(evaluate("(1 m)^3000000000").unwrap_err(), "Overflow");
+            assert_eq!(evaluate("(1 m)^2000000000 / (1 m)^2000000000").unwrap(), "1");
+        }
+    }
+}
@@ -0,0 +1,497 @@
+#[allow(dead_code)]
+pub mod word{
+    use std::fmt;
+    use crate::calculator_model::model::{
+        parse_to_tree_with,
+        EvalError,
+        Function,
+        Node,
+        Operator,
+        Tree,
+        Value
+    };
+    use crate::calculator_number::number::Number;
+    use crate::calculator_format::format;
+
+    // Machine integer type, u8 through i128.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Width{
+        pub bits: u32,
+        pub signed: bool
+    }
+
+    impl Width{
+        pub fn from_name(name: &str) -> Option<Width>{
+            let signed = match name.get(..1){
+                Some("u") => false,
+                Some("i") => true,
+                _ => return None
+            };
+            match name[1..].parse(){
+                Ok(bits @ (8 | 16 | 32 | 64 | 128)) => Some(Width{bits, signed}),
+                _ => None
+            }
+        }
+
+        fn mask(&self) -> u128{
+            u128::MAX >> (128 - self.bits)
+        }
+
+        // Largest and smallest values as raw bits.
+        fn max(&self) -> u128{
+            if self.signed {self.mask() >> 1} else {self.mask()}
+        }
+
+        fn min(&self) -> u128{
+            if self.signed {(self.mask() >> 1) + 1} else {0}
+        }
+    }
+
+    impl fmt::Display for Width{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            write!(f, "{}{}", if self.signed {"i"} else {"u"}, self.bits)
+        }
+    }
+
+    // What happens when a result does not fit the width.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Overflow{
+        Wrapping,
+        Checked,
+        Saturating
+    }
+
+    impl Overflow{
+        pub fn from_name(name: &str) -> Option<Overflow>{
+            match name{
+                "wrapping" | "wrap" => Some(Overflow::Wrapping),
+                "checked" => Some(Overflow::Checked),
+                "saturating" | "saturate" => Some(Overflow::Saturating),
+                _ => None
+            }
+        }
+    }
+
+    // Two's complement bits of a fixed width integer. Zero and one from the Number trait have no
+    // width yet and take on the width of whatever they meet, they fit every width the same.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Word{
+        bits: u128,
+        kind: Option<(Width, Overflow)>
+    }
+
+    const UNSIZED: (Width, Overflow) = (Width{bits: 128, signed: true}, Overflow::Checked);
+
+    impl Word{
+        pub fn new(value: i128, width: Width, overflow: Overflow) -> Word{
+            Word{bits: value as u128 & width.mask(), kind: Some((width, overflow))}
+        }
+
+        // Decimal digits, too large a literal is handled like any other overflow.
+        pub fn parse_literal(src: &str, width: Width, overflow: Overflow) -> Result<Word, &'static str>{
+            Word::from_magnitude(parse_magnitude(src)?, false, width, overflow)
+        }
+
+        // A signed width reaches one further below zero than above it.
+        fn from_magnitude(value: u128, negative: bool, width: Width, overflow: Overflow) -> Result<Word, &'static str>{
+            let word = Word{bits: 0, kind: Some((width, overflow))};
+            let (limit, bits, bound) = match negative{
+                true => (width.min(), value.wrapping_neg(), width.min()),
+                false => (width.max(), value, width.max())
+            };
+            match (value <= limit, overflow){
+                (true, _) | (false, Overflow::Wrapping) => Ok(word.with_bits(bits)),
+                (false, Overflow::Saturating) => Ok(word.with_bits(bound)),
+                (false, Overflow::Checked) => Err("Integer out of range")
+            }
+        }
+
+        fn kind(&self) -> (Width, Overflow){
+            self.kind.unwrap_or(UNSIZED)
+        }
+
+        pub fn width(&self) -> Width{
+            self.kind().0
+        }
+
+        // Same width and policy, the bits cut down to fit.
+        fn with_bits(&self, bits: u128) -> Word{
+            Word{bits: bits & self.width().mask(), kind: self.kind}
+        }
+
+        // Borrows the width of the other operand when this one has none.
+        fn sized(&self, other: &Word) -> Word{
+            Word{bits: self.bits, kind: self.kind.or(other.kind)}
+        }
+
+        pub fn is_negative(&self) -> bool{
+            let width = self.width();
+            width.signed && self.bits >> (width.bits - 1) == 1
+        }
+
+        // The value sign extended to 128 bits.
+        fn signed(&self) -> i128{
+            if self.is_negative() {(self.bits | !self.width().mask()) as i128} else {self.bits as i128}
+        }
+
+        fn value(&self) -> Option<i128>{
+            if self.width().signed {Some(self.signed())} else {i128::try_from(self.bits).ok()}
+        }
+
+        // Wrapped bits when the exact result is out of range, with high telling which side it left by.
+        fn settle(&self, wrapped: u128, out_of_range: Option<bool>) -> Result<Word, EvalError>{
+            let width = self.width();
+            match (out_of_range, self.kind().1){
+                (None, _) | (Some(_), Overflow::Wrapping) => Ok(self.with_bits(wrapped)),
+                (Some(high), Overflow::Saturating) => Ok(self.with_bits(if high {width.max()} else {width.min()})),
+                (Some(_), Overflow::Checked) => Err(EvalError::Overflow)
+            }
+        }
+
+        fn arith(&self, op: Operator, other: &Word) -> Result<Word, EvalError>{
+            let (a, b) = (self.sized(other), other.sized(self));
+            let width = a.width();
+            if matches!(op, Operator::Div) && b.bits == 0{
+                return Err(EvalError::DivisionByZero);
+            }
+            if width.signed{
+                let (x, y) = (a.signed(), b.signed());
+                let (value, wrapped) = match op{
+                    Operator::Plus => x.overflowing_add(y),
+                    Operator::Minus => x.overflowing_sub(y),
+                    Operator::Mult => x.overflowing_mul(y),
+                    _ => x.overflowing_div(y)
+                };
+                // Past 128 bits the operands tell the direction, within them the exact value does.
+                let out_of_range = match op{
+                    _ if !wrapped && value > a.with_bits(width.max()).signed() => Some(true),
+                    _ if !wrapped && value < a.with_bits(width.min()).signed() => Some(false),
+                    _ if !wrapped => None,
+                    Operator::Plus => Some(y > 0),
+                    Operator::Minus => Some(y < 0),
+                    Operator::Mult => Some((x < 0) == (y < 0)),
+                    _ => Some(true)
+                };
+                a.settle(value as u128, out_of_range)
+            }else{
+                let (x, y) = (a.bits, b.bits);
+                let (value, wrapped) = match op{
+                    Operator::Plus => x.overflowing_add(y),
+                    Operator::Minus => x.overflowing_sub(y),
+                    Operator::Mult => x.overflowing_mul(y),
+                    _ => (x / y, false)
+                };
+                let out_of_range = match op{
+                    Operator::Minus if wrapped => Some(false),
+                    _ if wrapped || value > width.max() => Some(true),
+                    _ => None
+                };
+                a.settle(value, out_of_range)
+            }
+        }
+
+        // Wrapping needs every bit, so it squares. The other policies multiply step by step until the value hits a bound.
+        fn power(&self, exp: &Word) -> Result<Word, EvalError>{
+            let base = self.sized(exp);
+            if exp.is_negative(){
+                return Err(EvalError::NotExact);
+            }
+            let mut n = exp.bits;
+            let mut out = base.with_bits(1);
+            if base.kind().1 == Overflow::Wrapping{
+                let mut square = base;
+                while n > 0{
+                    if n & 1 == 1{
+                        out = out.arith(Operator::Mult, &square)?;
+                    }
+                    square = square.arith(Operator::Mult, &square)?;
+                    n >>= 1;
+                }
+                return Ok(out);
+            }
+            // Any base past -1..=1 runs out of 128 bits well before 130 steps, and the parity keeps the sign.
+            if n > 130{
+                n = 128 + n % 2;
+            }
+            for _ in 0..n{
+                out = out.arith(Operator::Mult, &base)?;
+            }
+            Ok(out)
+        }
+
+        fn factorial(&self) -> Result<Word, EvalError>{
+            if self.is_negative(){
+                return Err(EvalError::NotExact);
+            }
+            let width = self.width();
+            let mut out = self.with_bits(1);
+            let mut i = self.with_bits(2);
+            // Stops early once the product wraps to zero or saturates, it stays there.
+            while i.bits <= self.bits && out.bits != 0 && out.bits != width.max(){
+                out = out.arith(Operator::Mult, &i)?;
+                i = i.with_bits(i.bits + 1);
+                if i.bits == 0{
+                    break;
+                }
+            }
+            Ok(out)
+        }
+
+        fn binomial(&self, k: &Word) -> Result<Word, EvalError>{
+            let n = self.sized(k);
+            let (n_value, k_value) = match (n.value(), k.value()){
+                (Some(n), Some(k)) if n >= 0 && k >= 0 => (n as u128, k as u128),
+                _ => return Err(EvalError::NotExact)
+            };
+            if k_value > n_value{
+                return Ok(n.with_bits(0));
+            }
+            let k_value = k_value.min(n_value - k_value);
+            let mut exact = 1u128;
+            for i in 1..=k_value{
+                exact = match exact.checked_mul(n_value - k_value + i){
+                    Some(product) => product / i,
+                    // Past 128 bits there are no wrapped bits to give back.
+                    None if n.kind().1 == Overflow::Saturating => return Ok(n.with_bits(n.width().max())),
+                    None => return Err(EvalError::Overflow)
+                };
+            }
+            n.settle(exact, if exact > n.width().max() {Some(true)} else {None})
+        }
+
+        // Shift counts outside the width wrap around, fail or push every bit out, as the policy says.
+        fn shift(&self, op: Operator, count: &Word) -> Result<Word, EvalError>{
+            let (a, count) = (self.sized(count), count.sized(self));
+            let (width, overflow) = a.kind();
+            let n = match count.value(){
+                Some(n) if (0..width.bits as i128).contains(&n) => n as u32,
+                _ if overflow == Overflow::Wrapping => (count.bits % width.bits as u128) as u32,
+                _ if overflow == Overflow::Checked => return Err(EvalError::Overflow),
+                _ => width.bits
+            };
+            match op{
+                Operator::Shl if n >= width.bits => Ok(a.with_bits(0)),
+                Operator::Shl => Ok(a.with_bits(a.bits << n)),
+                _ if width.signed => Ok(a.with_bits((a.signed() >> n.min(127)) as u128)),
+                _ if n >= width.bits => Ok(a.with_bits(0)),
+                _ => Ok(a.with_bits(a.bits >> n))
+            }
+        }
+
+        // Rotations are taken modulo the width, a negative count turns the other way.
+        fn rotate(&self, left: bool, count: &Word) -> Result<Word, EvalError>{
+            let (a, count) = (self.sized(count), count.sized(self));
+            let bits = a.width().bits;
+            let n = count.signed().rem_euclid(bits as i128) as u32;
+            let n = if left {n} else {(bits - n) % bits};
+            if n == 0{
+                return Ok(a);
+            }
+            Ok(a.with_bits(a.bits << n | a.bits >> (bits - n)))
+        }
+
+        // Other bases show the bits themselves, so -1 in i8 is 0xFF.
+        pub fn to_radix(self, radix: u32) -> String{
+            if radix == 10 {self.to_string()} else {format::bits_to_radix(self.bits, radix)}
+        }
+    }
+
+    impl fmt::Display for Word{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+            if self.width().signed{
+                write!(f, "{}", self.signed())
+            }else{
+                write!(f, "{}", self.bits)
+            }
+        }
+    }
+
+    impl Number for Word{
+        // Without a width from the command line literals are checked i128.
+        fn from_literal(src: &str) -> Result<Self, &'static str>{
+            Word::parse_literal(src, UNSIZED.0, UNSIZED.1)
+        }
+
+        fn zero() -> Self{
+            Word{bits: 0, kind: None}
+        }
+
+        fn one() -> Self{
+            Word{bits: 1, kind: None}
+        }
+
+        fn to_f64(&self) -> f64{
+            if self.width().signed {self.signed() as f64} else {self.bits as f64}
+        }
+
+        fn add(&self, other: &Self) -> Result<Self, EvalError>{
+            self.arith(Operator::Plus, other)
+        }
+
+        fn sub(&self, other: &Self) -> Result<Self, EvalError>{
+            self.arith(Operator::Minus, other)
+        }
+
+        fn mul(&self, other: &Self) -> Result<Self, EvalError>{
+            self.arith(Operator::Mult, other)
+        }
+
+        // Truncates toward zero.
+        fn div(&self, other: &Self) -> Result<Self, EvalError>{
+            self.arith(Operator::Div, other)
+        }
+
+        fn pow(&self, other: &Self) -> Result<Self, EvalError>{
+            self.power(other)
+        }
+
+        fn call(&self, func: Function) -> Result<Self, EvalError>{
+            match func{
+                Function::Neg => self.with_bits(0).sub(self),
+                Function::Abs if self.is_negative() => self.with_bits(0).sub(self),
+                Function::Abs => Ok(*self),
+                Function::Not => Ok(self.with_bits(!self.bits)),
+                Function::Popcount => Ok(self.with_bits(self.bits.count_ones() as u128)),
+                Function::Fact => self.factorial(),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+
+        fn call_binary(&self, func: Function, other: &Self) -> Result<Self, EvalError>{
+            match func{
+                Function::Binom => self.binomial(other),
+                Function::Rotl => self.rotate(true, other),
+                Function::Rotr => self.rotate(false, other),
+                _ => Err(EvalError::NotExact)
+            }
+        }
+
+        fn bitwise(&self, op: Operator, other: &Self) -> Result<Self, EvalError>{
+            let (a, b) = (self.sized(other), other.sized(self));
+            match op{
+                Operator::And => Ok(a.with_bits(a.bits & b.bits)),
+                Operator::Or => Ok(a.with_bits(a.bits | b.bits)),
+                Operator::Xor => Ok(a.with_bits(a.bits ^ b.bits)),
+                Operator::Shl | Operator::Shr => a.shift(op, &b),
+                _ => op.apply(self, other)
+            }
+        }
+    }
+
+    fn parse_magnitude(src: &str) -> Result<u128, &'static str>{
+        if src.is_empty() || !src.bytes().all(|b| b.is_ascii_digit()){
+            return Err("Bad integer");
+        }
+        src.parse().map_err(|_| "Integer out of range")
+    }
+
+    // A minus straight in front of a literal is folded into it, so -128 fits in an i8 though 128 does not.
+    fn fold_negative(node: &Node<Value<u128>>, width: Width, overflow: Overflow) -> Result<Box<Node<Value<Word>>>, &'static str>{
+        let literal = |value: &u128, negative: bool| Word::from_magnitude(*value, negative, width, overflow);
+        let left = node.left_node();
+        if let (true, Some(Value::Function(Function::Neg)), Some(Some(Value::Value(value)))) = (width.signed, &node.value, left.map(|n| &n.value)){
+            return Ok(Node::with_children(Value::Value(literal(value, true)?), None, None));
+        }
+        let value = node.value.as_ref().ok_or("Empty expression")?.try_map(&|value| literal(value, false))?;
+        let left = match left{
+            Some(n) => Some(fold_negative(n, width, overflow)?),
+            None => None
+        };
+        let right = match node.right_node(){
+            Some(n) => Some(fold_negative(n, width, overflow)?),
+            None => None
+        };
+        Ok(Node::with_children(value, left, right))
+    }
+
+    pub fn parse_word(src: &str, width: Width, overflow: Overflow) -> Result<Tree<Value<Word>>, &'static str>{
+        let tree = parse_to_tree_with(src, parse_magnitude)?;
+        let root = tree.root_node().ok_or("Empty expression")?;
+        Ok(Tree::from_root(fold_negative(root, width, overflow)?))
+    }
+
+    #[cfg(test)]
+    mod tests{
+        use std::collections::HashMap;
+        use super::*;
+        use crate::calculator_model::model::parse_to_tree;
+        use crate::calculator_json::json;
+        use crate::calculator_rpn::rpn;
+        use crate::calculator_sexpr::sexpr;
+
+        fn evaluate(src: &str, width: &str, overflow: Overflow) -> String{
+            let width = Width::from_name(width).unwrap();
+            match parse_word(src, width, overflow){
+                Ok(tree) => match tree.evaluate_number(&HashMap::new()){
+                    Ok(num) => num.to_string(),
+                    Err(e) => e.to_string()
+                },
+                Err(e) => e.to_string()
+            }
+        }
+
+        fn checked(src: &str, width: &str) -> String{
+            evaluate(src, width, Overflow::Checked)
+        }
+
+        #[test]
+        fn minimum_and_maximum_literals(){
+            assert_eq!(checked("-128", "i8"), "-128");
+            assert_eq!(checked("127", "i8"), "127");
+            assert_eq!(checked("128", "i8"), "Integer out of range");
+            assert_eq!(checked("-129", "i8"), "Integer out of range");
+            assert_eq!(checked("255", "u8"), "255");
+            assert_eq!(checked("-170141183460469231731687303715884105728", "i128"), "-170141183460469231731687303715884105728");
+            assert_eq!(checked("340282366920938463463374607431768211455", "u128"), "340282366920938463463374607431768211455");
+            assert_eq!(checked("-(-128)", "i8"), "Overflow");
+        }
+
+        #[test]
+        fn overflow_policies(){
+            assert_eq!(checked("255 + 1", "u8"), "Overflow");
+            assert_eq!(evaluate("255 + 1", "u8", Overflow::Wrapping), "0");
+            assert_eq!(evaluate("255 + 1", "u8", Overflow::Saturating), "255");
+            assert_eq!(evaluate("-128 - 1", "i8", Overflow::Saturating), "-128");
+            assert_eq!(evaluate("300", "u8", Overflow::Saturating), "255");
+        }
+
+        #[test]
+        fn bitwise_operations(){
+            assert_eq!(checked("0xF0 & 0x3C | 1", "u8"), "49");
+            assert_eq!(checked("~0", "u8"), "255");
+            assert_eq!(checked("popcount(255)", "u8"), "8");
+            assert_eq!(checked("rotl(129, 1)", "u8"), "3");
+            assert_eq!(checked("rotr(rotl(200, 3), 3)", "u8"), "200");
+        }
+
+        #[test]
+        fn division_truncates_and_can_overflow(){
+            assert_eq!(checked("-7 / 2", "i8"), "-3");
+            assert_eq!(checked("255 / 16", "u8"), "15");
+            assert_eq!(checked("-128 / -1", "i8"), "Overflow");
+            assert_eq!(evaluate("-128 / -1", "i8", Overflow::Wrapping), "-128");
+            assert_eq!(evaluate("-128 / -1", "i8", Overflow::Saturating), "127");
+            assert_eq!(checked("5 / 0", "u8"), "DivisionByZero");
+            assert_eq!(evaluate("5 / 0", "i32", Overflow::Wrapping), "DivisionByZero");
+        }
+
+        #[test]
+        fn bitwise_functions_round_trip(){
+            let tree = parse_to_tree("rotl(129, 1) + ~popcount(7)").unwrap();
+            let infix = "rotl(129, 1) + not(popcount(7))";
+            assert_eq!(tree.to_string(), infix);
+            assert_eq!(json::parse_json(&tree.to_json()).ok().unwrap().to_string(), infix);
+            assert_eq!(sexpr::parse_sexpr(&tree.to_sexpr()).unwrap().to_string(), infix);
+            assert_eq!(rpn::parse_rpn(&tree.to_rpn()).unwrap().to_string(), infix);
+            assert_eq!(tree.to_sexpr(), "(+ (rotl 129 1) (not (popcount 7)))");
+        }
+
+        #[test]
+        fn bitwise_functions_in_markup(){
+            let tree = parse_to_tree("rotr(~x, 2)").unwrap();
+            assert_eq!(tree.to_latex(), r"\operatorname{rotr}\left(\lnot x, 2\right)");
+            assert!(tree.to_mathml().contains("<mi>rotr</mi><mo>&#x2061;</mo>"));
+            assert!(tree.to_mathml().contains("<mo>&#x00AC;</mo>"));
+        }
+    }
+}
@@ -1,12 +1,360 @@
 mod calculator_model;
-use calculator_model::model;
+mod calculator_bytecode;
+mod calculator_rpn;
+mod calculator_infix;
+mod calculator_dot;
+mod calculator_markup;
+mod calculator_json;
+mod calculator_sexpr;
+mod calculator_simplify;
+mod calculator_derive;
+mod calculator_solve;
+mod calculator_calculus;
+mod calculator_bigint;
+mod calculator_rational;
+mod calculator_decimal;
+mod calculator_number;
+mod calculator_complex;
+mod calculator_interval;
+mod calculator_uncertain;
+mod calculator_units;
+mod calculator_currency;
+mod calculator_datetime;
+mod calculator_word;
+mod calculator_format;
+use std::{
+    collections::HashMap,
+    env,
+    io
+};
+use calculator_model::model::{
+    self,
+    Env,
+    Layout,
+    Tree,
+    Value
+};
+use calculator_bytecode::bytecode;
+use calculator_calculus::calculus::Quadrature;
+use calculator_rpn::rpn;
+use calculator_json::json;
+use calculator_sexpr::sexpr;
+use calculator_derive::derive;
+use calculator_solve::solve;
+use calculator_decimal::decimal::{
+    self,
+    DecimalContext,
+    Rounding
+};
+use calculator_number::number::Number;
+use calculator_rational::rational::{
+    IntegerDivision,
+    Rational
+};
+use calculator_bigint::bigint::BigInt;
+use calculator_complex::complex;
+use calculator_interval::interval::Interval;
+use calculator_uncertain::uncertain::{
+    self,
+    Uncertain
+};
+use calculator_units::units::UnitTable;
+use calculator_currency::currency::RateTable;
+use calculator_datetime::datetime;
+use calculator_format::format::{
+    self,
+    Format,
+    Notation
+};
+use calculator_word::word::{
+    self,
+    Overflow,
+    Width
+};
+// Digits after the point printed by --exact-digits.
+const DECIMAL_PLACES: usize = 30;
+const FLAGS: [&str; 21] = [
+    "--rpn", "--json", "--sexpr", "--simplify", "--derive", "--solve", "--exact", "--exact-digits", "--units", "--dates", "--group", "--trim", "--bench",
+    "--to-rpn", "--to-infix", "--tree", "--to-dot", "--to-latex", "--to-mathml", "--to-json", "--to-sexpr"
+];
+// Options that take a value, written as --name=value.
+const OPTIONS: [&str; 12] = [
+    "--scale=", "--round=", "--number=", "--division=", "--unit=", "--rates=", "--overflow=", "--base=",
+    "--format=", "--precision=", "--group=", "--derive="
+];
+
+fn decimal_context(scale: Option<&str>, rounding: Option<&str>) -> Result<DecimalContext, &'static str>{
+    let mut ctx = DecimalContext::default();
+    if let Some(scale) = scale{
+        ctx.scale = scale.parse().map_err(|_| "Bad scale")?;
+    }
+    if let Some(rounding) = rounding{
+        ctx.rounding = Rounding::from_name(rounding).ok_or("Unknown rounding mode")?;
+    }
+    Ok(ctx)
+}
+
+// --group alone separates thousands with commas, --group=_ picks the separator.
+fn output_format(notation: Option<&str>, precision: Option<&str>, separator: Option<&str>, trim: bool, base: Option<&str>) -> Result<Format, &'static str>{
+    let mut format = Format{trim, ..Format::default()};
+    if let Some(notation) = notation{
+        format.notation = Notation::from_name(notation).ok_or("Unknown format")?;
+    }
+    if let Some(precision) = precision{
+        format.precision = precision.parse().map_err(|_| "Bad precision")?;
+    }
+    if let Some(separator) = separator{
+        let mut chars = separator.chars();
+        format.separator = match (chars.next(), chars.next()){
+            (Some(c), None) if format::is_separator(c) => Some(c),
+            _ => return Err("Bad separator")
+        };
+    }
+    if let Some(base) = base{
+        format.radix = format::radix_from_name(base).ok_or("Unknown base")?;
+    }
+    Ok(format)
+}
+
+// Integrals taken on the way say how far off they may be.
+fn with_estimate(result: &Quadrature, output: &Format) -> String{
+    match result.error{
+        error if error > 0f64 => format!("{} (error estimate {:e})", output.apply(result.value), error),
+        _ => output.apply(result.value)
+    }
+}
+
+fn evaluate_as<N: Number, F: Fn(&N) -> String>(src: &str, show: F) -> String{
+    evaluate_parsed(model::parse_to_tree_with(src, N::from_literal), show)
+}
+
+fn evaluate_parsed<N: Number, F: Fn(&N) -> String>(parsed: Result<Tree<Value<N>>, &str>, show: F) -> String{
+    match parsed{
+        Ok(tree) => {
+            match tree.evaluate_number(&HashMap::new()){
+                Ok(num) => show(&num),
+                Err(e) => e.to_string()
+            }
+        },
+        Err(e) => e.to_string()
+    }
+}
+
 fn main() {
-    // match model::parse_to_tree("5.5+5.5*2"){
-    //     Ok(_) => (),
-    //     Err(e) => println!("{}",e)
-    // }
-    match model::test(){
-        Ok(_) => (),
-        Err(e) => print!("{}",e)
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty(){
+        match model::test(){
+            Ok(_) => (),
+            Err(e) => print!("{}",e)
+        }
+        return;
+    }
+
+    let is_flag = |a: &String| FLAGS.contains(&a.as_str()) || OPTIONS.iter().any(|o| a.starts_with(o));
+    let flags: Vec<&String> = args.iter().filter(|a| is_flag(a)).collect();
+    let src = args.iter().filter(|a| !is_flag(a)).cloned().collect::<Vec<String>>().join(" ");
+    let has_flag = |name: &str| flags.iter().any(|f| f.as_str() == name);
+    // Times the tree walkers against the bytecode on a fixed expression.
+    if has_flag("--bench"){
+        if let Err(e) = bytecode::bench(){
+            print!("{}",e);
+        }
+        return;
+    }
+    let option = |name: &str| flags.iter().rev().find_map(|f| f.strip_prefix(name));
+    // Applies to f64 and unit results, fixed width integers only take the base.
+    let separator = if has_flag("--group") {option("--group=").or(Some(","))} else {option("--group=")};
+    let output = match output_format(option("--format="), option("--precision="), separator, has_flag("--trim"), option("--base=")){
+        Ok(output) => output,
+        Err(e) => {
+            println!("{}",e);
+            return;
+        }
+    };
+    let formatted = option("--format=").is_some() || option("--precision=").is_some() || separator.is_some() || has_flag("--trim");
+    let other_numbers = has_flag("--exact") || has_flag("--exact-digits") || has_flag("--dates") || option("--number=").is_some_and(|kind| kind != "f64");
+    if formatted && other_numbers && !has_flag("--units") && option("--rates=").is_none(){
+        println!("--format, --precision, --group and --trim only apply to f64 and unit results");
+        return;
+    }
+
+    if has_flag("--solve"){
+        match solve::parse_solve(&src){
+            Ok((tree, var, guess)) => {
+                match solve::solve(&tree, &var, guess, &Env::new()){
+                    Ok(root) => println!("{} = {}",var,output.apply(root)),
+                    Err(e) => println!("{}",e)
+                }
+            },
+            Err(e) => println!("{}",e)
+        }
+        return;
+    }
+
+    // Literals are read digit for digit as fractions, --exact-digits writes the fraction out as a decimal.
+    if has_flag("--exact-digits"){
+        println!("{}",evaluate_as(&src, |num: &Rational| num.to_decimal(DECIMAL_PLACES)));
+        return;
+    }
+    if has_flag("--exact"){
+        println!("{}",evaluate_as(&src, Rational::to_string));
+        return;
+    }
+
+    // Names are units, --unit=name=definition adds to the table and --rates=file brings in currencies.
+    if has_flag("--units") || option("--rates=").is_some(){
+        let mut table = UnitTable::default();
+        table.set_format(output);
+        if let Some(path) = option("--rates="){
+            match RateTable::load(path){
+                Ok(rates) => table.set_rates(rates),
+                Err(e) => {
+                    println!("{}",e);
+                    return;
+                }
+            }
+        }
+        for definition in flags.iter().filter_map(|f| f.strip_prefix("--unit=")){
+            if let Err(e) = table.define_str(definition){
+                println!("{}",e);
+                return;
+            }
+        }
+        match table.evaluate_str(&src){
+            Ok(result) | Err(result) => println!("{}",result)
+        }
+        return;
+    }
+
+    // Dates, times and durations, with "in hours" to count the result in a duration.
+    if has_flag("--dates"){
+        match datetime::evaluate_str(&src){
+            Ok(result) | Err(result) => println!("{}",result)
+        }
+        return;
+    }
+
+    if let Some(kind) = option("--number="){
+        let result = match kind{
+            "f64" => evaluate_as(&src, |num: &Quadrature| with_estimate(num, &output)),
+            "f32" => evaluate_as(&src, f32::to_string),
+            "rational" => evaluate_as(&src, Rational::to_string),
+            // Base 10 with literals read digit for digit, --scale= and --round= set how division ends.
+            "decimal" => {
+                let result = decimal_context(option("--scale="), option("--round="))
+                    .and_then(|ctx| Ok((decimal::parse_decimal(&src, ctx)?, ctx)));
+                match result{
+                    Ok((tree, ctx)) => {
+                        match tree.evaluate_decimal(&ctx){
+                            Ok(num) => num.to_string(),
+                            Err(e) => e.to_string()
+                        }
+                    },
+                    Err(e) => e.to_string()
+                }
+            },
+            "interval" => evaluate_as(&src, Interval::to_string),
+            "uncertain" => evaluate_parsed(uncertain::parse_uncertain(&src), Uncertain::to_string),
+            "complex" => {
+                match complex::parse_complex(&src){
+                    Ok(tree) => {
+                        match tree.evaluate_complex(){
+                            Ok(num) => num.to_string(),
+                            Err(e) => e.to_string()
+                        }
+                    },
+                    Err(e) => e.to_string()
+                }
+            },
+            "bigint" => {
+                match IntegerDivision::from_name(option("--division=").unwrap_or("error")){
+                    Some(division) => {
+                        match model::parse_to_tree_with(&src, BigInt::from_literal){
+                            Ok(tree) => {
+                                match tree.evaluate_integer(division){
+                                    Ok(num) => num.to_string(),
+                                    Err(e) => e.to_string()
+                                }
+                            },
+                            Err(e) => e.to_string()
+                        }
+                    },
+                    None => "Unknown division mode".to_string()
+                }
+            },
+            // u8 to i128, overflow checked unless --overflow=wrapping or saturating says otherwise.
+            _ => {
+                match (Width::from_name(kind), Overflow::from_name(option("--overflow=").unwrap_or("checked"))){
+                    (Some(width), Some(overflow)) => {
+                        match word::parse_word(&src, width, overflow){
+                            Ok(tree) => {
+                                match tree.evaluate_number(&HashMap::new()){
+                                    Ok(num) => num.to_radix(output.radix),
+                                    Err(e) => e.to_string()
+                                }
+                            },
+                            Err(e) => e.to_string()
+                        }
+                    },
+                    (None, _) => "Unknown number type".to_string(),
+                    (_, None) => "Unknown overflow mode".to_string()
+                }
+            }
+        };
+        println!("{}",result);
+        return;
+    }
+
+    let parsed = if has_flag("--rpn"){
+        rpn::parse_rpn(&src).map_err(|e| e.to_string())
+    }else if has_flag("--json"){
+        json::parse_json(&src).map_err(|e| e.to_string())
+    }else if has_flag("--sexpr"){
+        sexpr::parse_sexpr(&src).map_err(|e| e.to_string())
+    }else{
+        model::parse_to_tree(&src).map_err(|e| e.to_string())
+    };
+    let parsed = match parsed{
+        Ok(tree) if has_flag("--simplify") => tree.simplify().map_err(|e| e.to_string()),
+        other => other
+    };
+    // --derive alone differentiates by x, --derive=t by t.
+    let derive_var = if has_flag("--derive") {option("--derive=").or(Some("x"))} else {option("--derive=")};
+    let parsed = match (parsed, derive_var){
+        (Ok(tree), Some(var)) => derive::derivative(&tree, var).map_err(|e| e.to_string()),
+        (other, _) => other
+    };
+    match parsed{
+        Ok(tree) => {
+            if has_flag("--to-rpn"){
+                println!("{}",tree.to_rpn());
+            }
+            if has_flag("--to-infix"){
+                println!("{}",tree);
+            }
+            if has_flag("--tree"){
+                let _ = tree.render_io(&mut io::stdout(), Layout::Boxed);
+            }
+            if has_flag("--to-dot"){
+                print!("{}",tree.to_dot());
+            }
+            if has_flag("--to-latex"){
+                println!("{}",tree.to_latex());
+            }
+            if has_flag("--to-mathml"){
+                println!("{}",tree.to_mathml());
+            }
+            if has_flag("--to-json"){
+                println!("{}",tree.to_json());
+            }
+            if has_flag("--to-sexpr"){
+                println!("{}",tree.to_sexpr());
+            }
+            match tree.evaluate_estimate(){
+                Ok(num) => println!("{}",with_estimate(&num, &output)),
+                Err(e) => println!("{}",e)
+            }
+        },
+        Err(e) => println!("{}",e)
     }
 }